@@ -1,3 +1,14 @@
-mod lexer;
-mod parser;
-mod resolved_type_tag;
+pub mod ast_dump;
+pub mod decompile;
+pub mod diagnostic;
+pub mod fold;
+pub mod graph;
+pub mod lexer;
+pub mod output;
+pub mod parser;
+pub mod resolved_type_tag;
+pub mod span;
+pub mod stats;
+pub mod symbols;
+pub mod token_dump;
+pub mod type_check;
@@ -0,0 +1,137 @@
+use strum::EnumString;
+use crate::compiler::parser::{Identifier, RootAst, Statement, UnresolvedTypeName};
+use crate::compiler::parser::visitor::{Visitable, Visitor};
+
+#[derive(EnumString, Eq, PartialEq, Copy, Clone, Debug)]
+#[strum(serialize_all = "camelCase")]
+pub enum AstFormat {
+    /// Rust's pretty-printed `Debug` representation.
+    Debug,
+    /// one S-expression per statement, e.g. `(var a (Add 1 2))`.
+    Sexp,
+}
+
+pub fn dump(root: &RootAst, format: AstFormat) -> String {
+    match format {
+        AstFormat::Debug => format!("{root:#?}\n"),
+        AstFormat::Sexp => {
+            let mut printer = SExpressionPrinter::default();
+            root.accept(&mut printer);
+            printer.out
+        }
+    }
+}
+
+#[derive(Default)]
+struct SExpressionPrinter {
+    out: String,
+    /// whether each expression being walked has opened a parenthesis.
+    /// leaves, such as literals, do not have an operator and are printed bare.
+    expressions: Vec<bool>,
+    /// how many blocks are being walked
+    blocks: usize,
+}
+
+impl SExpressionPrinter {
+    fn atom(&mut self, atom: &str) {
+        if !(self.out.is_empty() || self.out.ends_with(['(', '\n'])) {
+            self.out.push(' ');
+        }
+        self.out.push_str(atom);
+    }
+}
+
+impl Visitor for SExpressionPrinter {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::NodeDeclaration { is_const: false, .. } => self.atom("(var"),
+            Statement::NodeDeclaration { is_const: true, .. } => self.atom("(const"),
+            Statement::Comment { content } => self.atom(&format!("(comment {content:?}")),
+            Statement::Expression(_) => self.atom("(expr"),
+            Statement::If { .. } => self.atom("(if"),
+            Statement::While { .. } => self.atom("(while"),
+            Statement::Assignment { .. } => self.atom("(set"),
+            Statement::For { .. } => self.atom("(for"),
+            Statement::NoMoreStatements => {}
+        }
+    }
+
+    fn leave_statement(&mut self, statement: &Statement) {
+        if !matches!(statement, Statement::NoMoreStatements) {
+            self.out.push(')');
+            // statements inside of a block stay on the line of the outermost statement
+            if self.blocks == 0 {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    fn enter_block(&mut self) {
+        self.atom("(block");
+        self.blocks += 1;
+    }
+
+    fn leave_block(&mut self) {
+        self.out.push(')');
+        self.blocks -= 1;
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.atom(identifier.as_str());
+    }
+
+    fn visit_type_name(&mut self, type_name: &UnresolvedTypeName) {
+        self.atom(&format!("(type {type_name})"));
+    }
+
+    fn visit_literal(&mut self, literal: &str) {
+        self.atom(literal);
+    }
+
+    fn visit_operator(&mut self, operator: &'static str) {
+        self.atom(&format!("({operator}"));
+        if let Some(opened) = self.expressions.last_mut() {
+            *opened = true;
+        }
+    }
+
+    fn enter_expression(&mut self) {
+        self.expressions.push(false);
+    }
+
+    fn leave_expression(&mut self) {
+        if self.expressions.pop() == Some(true) {
+            self.out.push(')');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::ast_dump::{dump, AstFormat};
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+
+    fn parse(source: &str) -> RootAst {
+        Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid")
+    }
+
+    #[test]
+    fn sexp_writes_one_line_per_top_level_statement() {
+        let root = parse("const c: i64 = \"s\"\nwhile a < 1\na = a + 1\nwend\nfor i = 1 to 2\nf(i)\nend\n");
+
+        assert_eq!(dump(&root, AstFormat::Sexp), "\
+(const c (type i64) \"s\")
+(while (Less a 1) (block (set a (Add a 1))))
+(for i 1 2 (block (expr (call f i))))
+");
+    }
+
+    #[test]
+    fn debug_is_the_pretty_printed_tree() {
+        let root = parse("1\n");
+
+        assert_eq!(dump(&root, AstFormat::Debug), format!("{root:#?}\n"));
+        assert!(dump(&root, AstFormat::Debug).starts_with("RootAst {\n"));
+    }
+}
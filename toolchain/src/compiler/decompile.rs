@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::compiler::graph::{from_csharp_name, is_numeric, NodeGraph};
+use crate::compiler::lexer::KEYWORDS;
+use crate::compiler::resolved_type_tag::TypeTag;
+use crate::stub::is_identifier;
+
+/// converts a node graph, such as what `compile --emit graph` writes, back into `var` declarations.
+/// every named node becomes a declaration, and so does a node whose output is not used.
+/// other nodes are written inline where they are used.
+pub fn decompile(json: &str) -> Result<String> {
+    let graph: NodeGraph = serde_json::from_str(json).context("failed to parse the node graph")?;
+    let mut decompiler = Decompiler::new(&graph)?;
+    for node in &graph.nodes {
+        if node.name.is_some() || !decompiler.used.contains(&node.id) {
+            decompiler.declare(node.id)?;
+        }
+    }
+
+    // only nodes which are used by each other are left, so this reports the cycle
+    for node in &graph.nodes {
+        if !decompiler.written.contains(&node.id) {
+            decompiler.declare(node.id)?;
+        }
+    }
+
+    Ok(decompiler.lines.iter().map(|line| format!("{line}\n")).collect())
+}
+
+/// an expression, and how tightly it binds so that its parent can tell whether it needs parentheses.
+struct Written {
+    text: String,
+    precedence: u8,
+}
+
+const ADDITIVE: u8 = 1;
+const MULTIPLICATIVE: u8 = 2;
+const PRIMARY: u8 = 3;
+
+struct Decompiler<'g> {
+    graph: &'g NodeGraph,
+    /// which output is connected to the input, keyed by the node and the name of the input.
+    sources: HashMap<(usize, &'g str), usize>,
+    /// nodes whose output is connected to something.
+    used: HashSet<usize>,
+    declared: HashSet<usize>,
+    written: HashSet<usize>,
+    /// nodes being written, to tell a cycle.
+    visiting: HashSet<usize>,
+    lines: Vec<String>,
+}
+
+impl<'g> Decompiler<'g> {
+    /// reports connections which do not make sense regardless of the node types.
+    fn new(graph: &'g NodeGraph) -> Result<Self> {
+        for (index, node) in graph.nodes.iter().enumerate() {
+            if node.id != index {
+                bail!("nodes[{index}].id: must be {index}, but it was {id}", id = node.id);
+            }
+
+            if let Some(name) = &node.name {
+                if !is_identifier(name) || KEYWORDS.contains(&name.as_str()) {
+                    bail!("nodes[{index}].name: `{name}` cannot be used as an identifier");
+                }
+            }
+        }
+
+        let mut sources = HashMap::new();
+        let mut used = HashSet::new();
+        for (index, connection) in graph.connections.iter().enumerate() {
+            for (end, endpoint) in [("from", &connection.from), ("to", &connection.to)] {
+                if endpoint.node >= graph.nodes.len() {
+                    bail!("connections[{index}].{end}.node: there is no node {node}", node = endpoint.node);
+                }
+            }
+
+            if sources.insert((connection.to.node, connection.to.port.as_str()), connection.from.node).is_some() {
+                bail!("connections[{index}].to: `{port}` of node {node} is connected more than once", port = connection.to.port, node = connection.to.node);
+            }
+
+            used.insert(connection.from.node);
+        }
+
+        Ok(Self {
+            graph,
+            sources,
+            used,
+            declared: HashSet::new(),
+            written: HashSet::new(),
+            visiting: HashSet::new(),
+            lines: vec![],
+        })
+    }
+
+    /// writes the declaration of the node, after the declarations it depends on.
+    fn declare(&mut self, id: usize) -> Result<()> {
+        if self.declared.contains(&id) {
+            return Ok(())
+        }
+
+        let value = self.write(id)?;
+        let name = self.name_of(id);
+        self.lines.push(format!("var {name}: {type_tag} = {value}", type_tag = self.type_of(id)?, value = value.text));
+        self.declared.insert(id);
+
+        Ok(())
+    }
+
+    /// a named node is referred by its name, and other nodes are written in place.
+    fn refer(&mut self, id: usize) -> Result<Written> {
+        if self.graph.nodes[id].name.is_none() {
+            return self.write(id)
+        }
+
+        self.declare(id)?;
+        Ok(Written { text: self.name_of(id), precedence: PRIMARY })
+    }
+
+    fn name_of(&self, id: usize) -> String {
+        self.graph.nodes[id].name.clone().unwrap_or_else(|| format!("node{id}"))
+    }
+
+    fn write(&mut self, id: usize) -> Result<Written> {
+        if !self.visiting.insert(id) {
+            bail!("node {id} depends on itself");
+        }
+
+        let graph = self.graph;
+        let node = &graph.nodes[id];
+        let type_tag = self.type_of(id)?;
+        let kind = node.node_type.split_once('<').map_or(node.node_type.as_str(), |(kind, _)| kind);
+        let (operator, precedence) = match kind {
+            "ValueInput" => {
+                let value = node.value.as_deref().ok_or_else(|| anyhow!("node {id}: `ValueInput` has no value"))?;
+                self.visiting.remove(&id);
+                self.written.insert(id);
+                return Ok(Written {
+                    text: literal(value, &type_tag).map_err(|error| anyhow!("node {id}: {error}"))?,
+                    precedence: PRIMARY,
+                })
+            }
+            "ValueAdd" => ("+", ADDITIVE),
+            "ValueSub" => ("-", ADDITIVE),
+            "ValueMul" => ("*", MULTIPLICATIVE),
+            "ValueDiv" => ("/", MULTIPLICATIVE),
+            "ValueMod" => ("%", MULTIPLICATIVE),
+            _ => bail!("node {id}: `{kind}` cannot be decompiled yet"),
+        };
+
+        if !is_numeric(&type_tag) {
+            bail!("node {id}: `{kind}` on {type_tag} is not supported");
+        }
+
+        let lhs = self.input(id, "A", &type_tag)?;
+        let rhs = self.input(id, "B", &type_tag)?;
+        // the operators are left-associative, so `a - (b - c)` needs the parentheses but `(a - b) - c` does not
+        let lhs_needs_parentheses = lhs.precedence < precedence;
+        let rhs_needs_parentheses = rhs.precedence <= precedence;
+        let lhs = parenthesize(lhs, lhs_needs_parentheses);
+        let rhs = parenthesize(rhs, rhs_needs_parentheses);
+        self.visiting.remove(&id);
+        self.written.insert(id);
+
+        Ok(Written {
+            text: format!("{lhs} {operator} {rhs}"),
+            precedence,
+        })
+    }
+
+    fn input(&mut self, id: usize, port: &str, expected: &TypeTag) -> Result<Written> {
+        let source = *self.sources.get(&(id, port))
+            .ok_or_else(|| anyhow!("node {id}: `{port}` is not connected"))?;
+        let actual = self.type_of(source)?;
+        if &actual != expected {
+            bail!("node {id}: `{port}` takes {expected}, but node {source} gives {actual}");
+        }
+
+        self.refer(source)
+    }
+
+    /// the type argument of the node, such as `i32` for `ValueAdd<int>`.
+    fn type_of(&self, id: usize) -> Result<TypeTag> {
+        let node_type = &self.graph.nodes[id].node_type;
+        let argument = node_type.strip_suffix('>')
+            .and_then(|s| s.split_once('<'))
+            .map(|(_, argument)| argument)
+            .ok_or_else(|| anyhow!("node {id}: `{node_type}` has no type argument"))?;
+
+        from_csharp_name(argument).ok_or_else(|| anyhow!("node {id}: `{argument}` cannot be decompiled yet"))
+    }
+}
+
+fn parenthesize(written: Written, needed: bool) -> String {
+    if needed {
+        format!("({text})", text = written.text)
+    } else {
+        written.text
+    }
+}
+
+/// writes the value so that it is read back as the same float literal. `1e10` is written as `10000000000.0`.
+fn float_spelling(value: impl Debug) -> String {
+    // Debug always writes a `.` or an exponent, and spells infinity as `inf`
+    match format!("{value:?}") {
+        nan if nan == "NaN" => "nan".to_string(),
+        spelling => spelling,
+    }
+}
+
+/// the spelling of the value in the source. an unsuffixed integral literal is i32, so the others get a suffix.
+/// a float literal has no suffix, but every operand has the type of the declaration it is written in, so it is read back as such.
+fn literal(value: &str, type_tag: &TypeTag) -> Result<String> {
+    let invalid = || anyhow!("`{value}` cannot be written as a literal of {type_tag}");
+    match type_tag {
+        TypeTag::Bool => matches!(value, "true" | "false").then(|| value.to_string()).ok_or_else(invalid),
+        TypeTag::String => Ok(format!("{value:?}")),
+        TypeTag::F32 => value.parse::<f32>().map(float_spelling).map_err(|_| invalid()),
+        TypeTag::F64 => value.parse::<f64>().map(float_spelling).map_err(|_| invalid()),
+        _ => {
+            let digits = value.strip_prefix('-').unwrap_or(value);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid())
+            }
+
+            if !type_tag.can_hold(value) {
+                bail!("`{value}` is out of range for {type_tag}");
+            }
+
+            if type_tag == &TypeTag::I32 {
+                Ok(value.to_string())
+            } else {
+                Ok(format!("{value}{type_tag}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::decompile::decompile;
+    use crate::compiler::graph::NodeGraph;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::resolved_type_tag::TypeTag;
+
+    fn compile(source: &str) -> String {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        let graph = NodeGraph::compile(&root, &TypeTag::I32).expect("the source can be compiled");
+        serde_json::to_string(&graph).expect("failed to serialize")
+    }
+
+    #[test]
+    fn compiled_declarations_are_written_back_with_only_the_needed_parentheses() {
+        let source = "var a: i32 = 1\nvar b: i32 = (a - 2) * 3\nvar c: i32 = a - (b - 1) + 4\n";
+
+        assert_eq!(decompile(&compile(source)).expect("the graph is valid"), source);
+    }
+
+    #[test]
+    fn node_whose_id_is_not_its_index_is_rejected() {
+        let json = compile("var a = 1\n").replace("\"id\":0", "\"id\":1");
+        let error = decompile(&json).expect_err("the id does not match the index");
+
+        assert_eq!(error.to_string(), "nodes[0].id: must be 0, but it was 1");
+    }
+
+    #[test]
+    fn f64_literal_is_written_back() {
+        let source = "var a: f64 = 1.5\nvar b: f64 = a + 0.1\n";
+
+        assert_eq!(decompile(&compile(source)).expect("the graph is valid"), source);
+    }
+
+    #[test]
+    fn f64_literal_in_an_operand_is_written_in_place() {
+        let source = "var a: f64 = 1.5 * 2.0\n";
+
+        assert_eq!(decompile(&compile(source)).expect("the graph is valid"), source);
+    }
+
+    #[test]
+    fn integral_literal_out_of_range_is_rejected() {
+        let json = compile("var a: u8 = 200\n").replace("\"200\"", "\"300\"");
+        let error = decompile(&json).expect_err("300 does not fit in u8");
+
+        assert_eq!(error.to_string(), "node 0: `300` is out of range for u8");
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+use serde::Serialize;
+use crate::compiler::lexer::LexError;
+use crate::compiler::parser::error::ParseError;
+use crate::compiler::span::{LineIndex, Span};
+use crate::compiler::type_check::TypeError;
+
+/// An error in the input, which `--error-format` decides how to print.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Diagnostic {
+    /// what is wrong, without where it is
+    pub message: String,
+    pub severity: Severity,
+    pub span: Option<Span>,
+}
+
+#[derive(Serialize, Debug, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// warnings are only logged so far
+    Error,
+}
+
+impl Diagnostic {
+    /// an error which is not tied to a position, such as one found while compiling.
+    pub fn error(message: String) -> Self {
+        Self {
+            message,
+            severity: Severity::Error,
+            span: None,
+        }
+    }
+
+    /// writes the diagnostic as a JSON object in a single line, for `--error-format json`.
+    /// `source` is the content of `file`, which resolves the span into lines and columns.
+    pub fn to_json(&self, file: &Path, source: &str) -> String {
+        let span = self.span.map(|span| {
+            let (line, column) = LineIndex::new(source).line_col(span.start);
+
+            JsonSpan {
+                file: file.display().to_string(),
+                line,
+                column,
+                byte_start: byte_offset(source, span.start),
+                byte_end: byte_offset(source, span.end),
+            }
+        });
+
+        let json = JsonDiagnostic {
+            message: &self.message,
+            severity: self.severity,
+            span,
+        };
+
+        serde_json::to_string(&json).expect("failed to serialize")
+    }
+
+    /// writes the message and where it starts, such as `... (at 2:9)`, for `--error-format human`.
+    /// `line_index` is built from the source, once for every diagnostic in it.
+    pub fn to_human(&self, line_index: &LineIndex) -> String {
+        match self.span {
+            Some(span) => {
+                let (line, column) = line_index.line_col(span.start);
+                format!("{message} (at {line}:{column})", message = self.message)
+            }
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Self {
+            message: error.message(),
+            severity: Severity::Error,
+            span: Some(error.span()),
+        }
+    }
+}
+
+impl From<&TypeError> for Diagnostic {
+    fn from(error: &TypeError) -> Self {
+        Self {
+            message: error.message.clone(),
+            severity: Severity::Error,
+            span: error.span,
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    /// the lexer only knows where the malformed token starts, so the span is empty.
+    fn from(error: &LexError) -> Self {
+        Self {
+            message: error.kind.to_string(),
+            severity: Severity::Error,
+            span: Some(Span {
+                start: error.index,
+                end: error.index,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    message: &'a str,
+    severity: Severity,
+    span: Option<JsonSpan>,
+}
+
+#[derive(Serialize)]
+struct JsonSpan {
+    file: String,
+    /// 1-origin, as [LineIndex::line_col]
+    line: usize,
+    /// 1-origin, counted in chars
+    column: usize,
+    /// [Span] counts chars, but editors tend to want bytes
+    byte_start: usize,
+    byte_end: usize,
+}
+
+/// converts an offset in chars into the one in bytes. an offset past the end is clamped to the end.
+fn byte_offset(source: &str, char_offset: usize) -> usize {
+    source.char_indices()
+        .nth(char_offset)
+        .map_or(source.len(), |(byte_offset, _)| byte_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::compiler::diagnostic::Diagnostic;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::span::{LineIndex, Span};
+    use crate::compiler::type_check::TypeError;
+
+    #[test]
+    fn parse_error_is_written_as_a_line_of_json() {
+        let source = "var = 1\n";
+        let errors = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect_err("the name is missing");
+        let json = Diagnostic::from(&errors[0]).to_json(Path::new("a.lax"), source);
+
+        assert_eq!(
+            json,
+            r#"{"message":"SymEq is unexpected, expected an identifier after `var`","severity":"error","span":{"file":"a.lax","line":1,"column":5,"byte_start":4,"byte_end":5}}"#
+        );
+    }
+
+    #[test]
+    fn span_is_written_in_lines_and_bytes() {
+        let source = "// ✓\nvar a = \"a\" - 1\n";
+        let error = TypeError {
+            message: "Subtract cannot be applied to string and i32".to_string(),
+            span: Some(Span { start: 13, end: 20 }),
+        };
+        let json = Diagnostic::from(&error).to_json(Path::new("a.lax"), source);
+
+        assert_eq!(
+            json,
+            r#"{"message":"Subtract cannot be applied to string and i32","severity":"error","span":{"file":"a.lax","line":2,"column":9,"byte_start":15,"byte_end":22}}"#
+        );
+    }
+
+    #[test]
+    fn diagnostic_without_span_has_null_span() {
+        let json = Diagnostic::error("failed".to_string()).to_json(Path::new("a.lax"), "");
+
+        assert_eq!(json, r#"{"message":"failed","severity":"error","span":null}"#);
+    }
+
+    #[test]
+    fn human_format_is_the_message_and_the_line_and_column_of_the_start() {
+        let error = TypeError {
+            message: "`b` is not declared".to_string(),
+            span: Some(Span { start: 10, end: 11 }),
+        };
+
+        assert_eq!(Diagnostic::from(&error).to_human(&LineIndex::new("var a = 1\nb\n")), "`b` is not declared (at 2:1)");
+        assert_eq!(Diagnostic::error("failed".to_string()).to_human(&LineIndex::new("")), "failed");
+    }
+}
@@ -0,0 +1,348 @@
+use crate::compiler::parser::{ElseIfBranch, RootAst, Statement};
+use crate::compiler::parser::expression::{
+    Additive, BinaryOperatorNode, BitwiseAndExpression, BitwiseOrExpression, BitwiseShift, BitwiseXorExpression, Cast,
+    Conditional, EqualityCheckExpression, Expression, First, LogicalAndExpression, LogicalOrExpression, MatchArm,
+    Multiplicative, PropagateFrom, RelationCheckExpression, Unary,
+};
+use crate::compiler::span::Span;
+use crate::interpreter::{CanBeEvaluated, Interpreter, InterpreterError, SupportedTypeBox, SupportedTypeTag};
+
+/// replaces each subexpression which only consists of literals and operators with its value, such as `1 + 2 * 3` with `7`.
+/// the operators are evaluated by [Interpreter]. an operator which fails, such as an overflow or a division by zero, is kept as is,
+/// since it may never be evaluated, as in `false && 1 / 0 == 0`.
+/// a subexpression is kept as is if its value cannot be written as a literal, such as a float.
+/// an unsuffixed integral literal is `default_int`, and the value of unsuffixed literals is written unsuffixed too.
+pub fn fold_constants(expression: Expression, default_int: SupportedTypeTag) -> Result<Expression, InterpreterError> {
+    expression.fold(default_int)
+}
+
+/// folds every expression in the program with [fold_constants].
+pub fn fold_program(root: RootAst, default_int: SupportedTypeTag) -> Result<RootAst, InterpreterError> {
+    fold_block(root.into_commands(), default_int).map(RootAst::from_commands)
+}
+
+fn fold_block(block: Vec<Statement>, default_int: SupportedTypeTag) -> Result<Vec<Statement>, InterpreterError> {
+    block.into_iter().map(|statement| fold_statement(statement, default_int)).collect()
+}
+
+fn fold_statement(statement: Statement, default_int: SupportedTypeTag) -> Result<Statement, InterpreterError> {
+    let fold = |expression: Expression| fold_constants(expression, default_int);
+    let fold_block = |block: Vec<Statement>| fold_block(block, default_int);
+    let folded = match statement {
+        Statement::NodeDeclaration { attributes, is_const, identifier, type_tag, rhs } => Statement::NodeDeclaration {
+            attributes,
+            is_const,
+            identifier,
+            type_tag,
+            rhs: fold(rhs)?,
+        },
+        Statement::Expression(expression) => Statement::Expression(fold(expression)?),
+        Statement::If { condition, then_block, elseif_branches, else_block } => Statement::If {
+            condition: fold(condition)?,
+            then_block: fold_block(then_block)?,
+            elseif_branches: elseif_branches.into_iter()
+                .map(|branch| Ok(ElseIfBranch {
+                    condition: fold(branch.condition)?,
+                    block: fold_block(branch.block)?,
+                }))
+                .collect::<Result<_, InterpreterError>>()?,
+            else_block: else_block.map(fold_block).transpose()?,
+        },
+        Statement::While { condition, body } => Statement::While {
+            condition: fold(condition)?,
+            body: fold_block(body)?,
+        },
+        Statement::Assignment { target, rhs } => Statement::Assignment {
+            target,
+            rhs: fold(rhs)?,
+        },
+        Statement::For { variable, from, to, body } => Statement::For {
+            variable,
+            from: fold(from)?,
+            to: fold(to)?,
+            body: fold_block(body)?,
+        },
+        other @ (Statement::Comment { .. } | Statement::NoMoreStatements) => other,
+    };
+
+    Ok(folded)
+}
+
+trait Fold: Sized {
+    fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError>;
+}
+
+/// wraps a literal into the node, so that it can replace the node.
+trait Lift {
+    fn lift(literal: First) -> Self;
+}
+
+fn is_literal(first: Option<&First>) -> bool {
+    matches!(first, Some(First::IntegralLiteral { .. } | First::FloatLiteral { .. } | First::StringLiteral { .. } | First::True { .. } | First::False { .. }))
+}
+
+/// whether the operands of a node can be folded, and whether the folded literal needs a suffix.
+enum Operands {
+    NotConstant,
+    Literals {
+        /// an integral literal among them has a suffix
+        suffixed: bool,
+    },
+}
+
+impl Operands {
+    fn of(operands: &[Option<&First>]) -> Self {
+        if !operands.iter().all(|operand| is_literal(*operand)) {
+            return Operands::NotConstant
+        }
+
+        let suffixed = operands.iter().any(|operand| matches!(operand, Some(First::IntegralLiteral { suffix: Some(_), .. })));
+        Operands::Literals { suffixed }
+    }
+}
+
+/// [First] cannot be cloned, but a literal can be written again.
+fn copy_literal(first: Option<&First>) -> Option<First> {
+    match first? {
+        First::IntegralLiteral { sequence, suffix, span } => Some(First::IntegralLiteral { sequence: sequence.clone(), suffix: suffix.clone(), span: *span }),
+        First::FloatLiteral { sequence, span } => Some(First::FloatLiteral { sequence: sequence.clone(), span: *span }),
+        First::StringLiteral { sequence, span } => Some(First::StringLiteral { sequence: sequence.clone(), span: *span }),
+        First::True { span } => Some(First::True { span: *span }),
+        First::False { span } => Some(First::False { span: *span }),
+        _ => None,
+    }
+}
+
+/// the suffix is written unless `unsuffixed`, so that the literal keeps its type regardless of the context.
+/// `span` is where the folded expression was.
+fn literal_of(value: SupportedTypeBox, span: Span, unsuffixed: bool) -> Option<First> {
+    let integral = |sequence: String| Some(First::IntegralLiteral {
+        sequence,
+        suffix: (!unsuffixed).then(|| value.tag().name().to_string()),
+        span,
+    });
+
+    match &value {
+        SupportedTypeBox::I8(v) => integral(v.to_string()),
+        SupportedTypeBox::U8(v) => integral(v.to_string()),
+        SupportedTypeBox::I16(v) => integral(v.to_string()),
+        SupportedTypeBox::U16(v) => integral(v.to_string()),
+        SupportedTypeBox::I32(v) => integral(v.to_string()),
+        SupportedTypeBox::U32(v) => integral(v.to_string()),
+        SupportedTypeBox::I64(v) => integral(v.to_string()),
+        SupportedTypeBox::U64(v) => integral(v.to_string()),
+        SupportedTypeBox::Bool(true) => Some(First::True { span }),
+        SupportedTypeBox::Bool(false) => Some(First::False { span }),
+        SupportedTypeBox::String(s) => Some(First::StringLiteral { sequence: s.clone(), span }),
+        // a float literal has no suffix, so it would become f64 in `var x: f64 = 0.1 + 0.2` while the sum is f32
+        SupportedTypeBox::F32(_) | SupportedTypeBox::F64(_) | SupportedTypeBox::List(_) => None,
+    }
+}
+
+/// evaluates the node if its operands are literals, and replaces it with the value.
+/// `span` is where the node is, which the literal takes over.
+/// the value of unsuffixed operands is left unsuffixed if it is `default_int`, as `1 + 2` becomes `3`.
+/// the node is left unfolded if it fails, so that the error is reported only if the node is evaluated.
+fn fold_if_constant<T: CanBeEvaluated + Lift>(node: T, span: Option<Span>, operands: Operands, default_int: SupportedTypeTag) -> Result<T, InterpreterError> {
+    let Operands::Literals { suffixed } = operands else {
+        return Ok(node)
+    };
+
+    let span = span.expect("the operands are literals, which know where they are");
+    let Ok(value) = Interpreter::new().with_default_int(default_int)?.evaluate(&node) else {
+        return Ok(node)
+    };
+    let unsuffixed = !suffixed && value.tag() == default_int;
+    Ok(literal_of(value, span, unsuffixed).map_or(node, T::lift))
+}
+
+impl Lift for First {
+    fn lift(literal: First) -> Self {
+        literal
+    }
+}
+
+impl Fold for First {
+    fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+        let folded = match self {
+            First::Parenthesized(inner) => {
+                let inner = inner.fold(default_int)?;
+                // the parentheses are no longer needed around a literal
+                match copy_literal(inner.as_first()) {
+                    Some(literal) => literal,
+                    None => First::Parenthesized(Box::new(inner)),
+                }
+            }
+            First::Call { callee, args } => First::Call {
+                callee,
+                args: args.into_iter().map(|arg| arg.fold(default_int)).collect::<Result<_, _>>()?,
+            },
+            First::Match { scrutinee, arms } => First::Match {
+                scrutinee: Box::new(scrutinee.fold(default_int)?),
+                arms: arms.into_iter()
+                    .map(|arm| Ok(MatchArm { pattern: arm.pattern, value: arm.value.fold(default_int)? }))
+                    .collect::<Result<_, InterpreterError>>()?,
+            },
+            First::List { elements } => First::List {
+                elements: elements.into_iter().map(|element| element.fold(default_int)).collect::<Result<_, _>>()?,
+            },
+            First::Index { target, index } => First::Index {
+                target: Box::new(target.fold(default_int)?),
+                index: Box::new(index.fold(default_int)?),
+            },
+            other => other,
+        };
+
+        Ok(folded)
+    }
+}
+
+impl Lift for Cast {
+    fn lift(literal: First) -> Self {
+        Cast::Propagated(literal)
+    }
+}
+
+impl Fold for Cast {
+    fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+        match self {
+            Cast::Do { operand, tp } => {
+                let operand = operand.fold(default_int)?;
+                let operands = Operands::of(&[operand.as_first()]);
+                let node = Cast::Do { operand: Box::new(operand), tp };
+                let span = node.span();
+                fold_if_constant(node, span, operands, default_int)
+            }
+            Cast::Propagated(first) => first.fold(default_int).map(Cast::Propagated),
+        }
+    }
+}
+
+impl Lift for Unary {
+    fn lift(literal: First) -> Self {
+        Unary::Propagated(Cast::lift(literal))
+    }
+}
+
+impl Fold for Unary {
+    fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+        match self {
+            Unary::Do { operator, operand } => {
+                let operand = operand.fold(default_int)?;
+                let operands = Operands::of(&[operand.as_first()]);
+                let node = Unary::Do { operator, operand: Box::new(operand) };
+                let span = node.span();
+                fold_if_constant(node, span, operands, default_int)
+            }
+            Unary::Propagated(cast) => cast.fold(default_int).map(Unary::Propagated),
+        }
+    }
+}
+
+macro_rules! fold_binary {
+    ($name:ident, $propagate_from:ident) => {
+        impl Lift for $name {
+            fn lift(literal: First) -> Self {
+                Self::propagate($propagate_from::lift(literal))
+            }
+        }
+
+        impl Fold for $name {
+            fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+                // from the left, as the chain is left-associative
+                let (first, rest) = self.into_operands();
+                let mut acc = first.fold(default_int).map($name::Propagated)?;
+                for (operator, rhs) in rest {
+                    let rhs = rhs.fold(default_int)?;
+                    let operands = Operands::of(&[acc.as_first(), rhs.as_first()]);
+                    let node = Self::binary(operator, acc, rhs);
+                    let span = node.span();
+                    acc = fold_if_constant(node, span, operands, default_int)?;
+                }
+
+                Ok(acc)
+            }
+        }
+    };
+}
+
+fold_binary!(Multiplicative, Unary);
+fold_binary!(Additive, Multiplicative);
+fold_binary!(BitwiseShift, Additive);
+fold_binary!(RelationCheckExpression, BitwiseShift);
+fold_binary!(EqualityCheckExpression, RelationCheckExpression);
+fold_binary!(BitwiseAndExpression, EqualityCheckExpression);
+fold_binary!(BitwiseXorExpression, BitwiseAndExpression);
+fold_binary!(BitwiseOrExpression, BitwiseXorExpression);
+fold_binary!(LogicalAndExpression, BitwiseOrExpression);
+fold_binary!(LogicalOrExpression, LogicalAndExpression);
+
+impl Lift for Conditional {
+    fn lift(literal: First) -> Self {
+        Conditional::Propagated(LogicalOrExpression::lift(literal))
+    }
+}
+
+impl Fold for Conditional {
+    fn fold(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+        match self {
+            Conditional::Do { condition, then, otherwise } => {
+                let condition = condition.fold(default_int)?;
+                // only the branch which is taken is left, even if it is not a constant
+                match condition.as_first() {
+                    Some(First::True { .. }) => then.fold(default_int),
+                    Some(First::False { .. }) => otherwise.fold(default_int),
+                    _ => Ok(Conditional::Do {
+                        condition: Box::new(condition),
+                        then: Box::new(then.fold(default_int)?),
+                        otherwise: Box::new(otherwise.fold(default_int)?),
+                    }),
+                }
+            }
+            Conditional::Propagated(inner) => inner.fold(default_int).map(Conditional::Propagated),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::ast_dump::{self, AstFormat};
+    use crate::compiler::fold::fold_program;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::interpreter::SupportedTypeTag;
+
+    fn fold(source: &str, default_int: SupportedTypeTag) -> String {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        let root = fold_program(root, default_int).expect("the source can be folded");
+        ast_dump::dump(&root, AstFormat::Sexp)
+    }
+
+    #[test]
+    fn literals_and_operators_are_folded_into_an_unsuffixed_literal() {
+        assert_eq!(fold("var a = 1 + 2 * 3\n", SupportedTypeTag::I32), "(var a 7)\n");
+    }
+
+    #[test]
+    fn only_the_constant_operand_is_folded() {
+        assert_eq!(fold("var a = b + 2 * 3\n", SupportedTypeTag::I32), "(var a (Add b 6))\n");
+    }
+
+    #[test]
+    fn suffixed_operand_keeps_the_suffix() {
+        assert_eq!(fold("var a = 1i64 + 2i64\n", SupportedTypeTag::I32), "(var a 3i64)\n");
+    }
+
+    #[test]
+    fn folding_respects_the_default_int() {
+        assert_eq!(fold("var a = 200 + 100\n", SupportedTypeTag::I32), "(var a 300)\n");
+        // 300 overflows u8, which is reported when it is evaluated
+        assert_eq!(fold("var a = 200 + 100\n", SupportedTypeTag::U8), "(var a (Add 200 100))\n");
+    }
+
+    #[test]
+    fn operator_which_fails_is_left_unfolded() {
+        assert_eq!(fold("var a = false && 1 / 0 == 0\n", SupportedTypeTag::I32), "(var a (LogicalAnd false (Equal (Divide 1 0) 0)))\n");
+        assert_eq!(fold("if false then\nvar c = 1 / 0\nend\n", SupportedTypeTag::I32), "(if false (block (var c (Divide 1 0))))\n");
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use crate::compiler::parser::{RootAst, Statement};
+use crate::compiler::parser::expression::{
+    Additive, AdditiveOps, BitwiseAndExpression, BitwiseOrExpression, BitwiseShift, BitwiseXorExpression, Cast,
+    Conditional, EqualityCheckExpression, First, LogicalAndExpression, LogicalOrExpression, Multiplicative,
+    MultiplicativeOps, RelationCheckExpression, Unary,
+};
+use crate::compiler::resolved_type_tag::TypeTag;
+
+/// What `compile --emit graph` writes out, in the shape Resonite imports as ProtoFlux nodes.
+/// only value nodes for literals and arithmetic are produced so far.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default)]
+pub struct NodeGraph {
+    pub nodes: Vec<GraphNode>,
+    pub connections: Vec<Connection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct GraphNode {
+    /// the index in [NodeGraph::nodes]
+    pub id: usize,
+    /// the node type with its type argument, such as `ValueAdd<int>`
+    #[serde(rename = "type")]
+    pub node_type: String,
+    /// the declared name, if the node is the value of a declaration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// the value held by a `ValueInput`, such as `1` or `text` without the quotes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub inputs: Vec<GraphPort>,
+    pub outputs: Vec<GraphPort>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct GraphPort {
+    pub name: String,
+    /// the C# name of the type, such as `int` or `float`
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+/// connects an output of a node to an input of another node.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct Connection {
+    pub from: Endpoint,
+    pub to: Endpoint,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct Endpoint {
+    pub node: usize,
+    pub port: String,
+}
+
+/// an output which a value has been lowered into.
+#[derive(Clone)]
+struct Wire {
+    node: usize,
+    port: &'static str,
+    type_tag: TypeTag,
+}
+
+struct GraphBuilder {
+    graph: NodeGraph,
+    declared: HashMap<String, Wire>,
+    /// the type of an unsuffixed integral literal
+    default_int: TypeTag,
+}
+
+impl NodeGraph {
+    /// fails if there is a statement or an expression which cannot be compiled into nodes yet.
+    /// an unsuffixed integral literal becomes a value of `default_int`.
+    pub fn compile(root: &RootAst, default_int: &TypeTag) -> Result<Self> {
+        let mut builder = GraphBuilder {
+            graph: NodeGraph::default(),
+            declared: HashMap::new(),
+            default_int: default_int.clone(),
+        };
+        for statement in root.commands() {
+            match statement {
+                Statement::NodeDeclaration { identifier, type_tag, rhs, .. } => {
+                    let declared = type_tag.as_ref()
+                        .map(|type_name| TypeTag::resolve(type_name).ok_or_else(|| anyhow!("the type of `{name}` is unknown", name = identifier.as_str())))
+                        .transpose()?;
+                    // an unsuffixed literal takes the declared type, as in `var a: i64 = 1 + 2`
+                    let wire = rhs.lower(&mut builder, declared.as_ref())?;
+                    if let Some(declared) = declared {
+                        if declared != wire.type_tag {
+                            bail!(
+                                "`{name}` is declared as {declared}, but its value is {actual}",
+                                name = identifier.as_str(),
+                                actual = wire.type_tag,
+                            );
+                        }
+                    }
+
+                    // `var b = a` does not add a node, so the node keeps the first name
+                    builder.graph.nodes[wire.node].name.get_or_insert_with(|| identifier.as_str().to_string());
+                    builder.declared.insert(identifier.as_str().to_string(), wire);
+                }
+                Statement::Comment { .. } | Statement::NoMoreStatements => {}
+                Statement::Expression(_) => bail!("expression statements cannot be compiled yet"),
+                Statement::If { .. } => bail!("if statements cannot be compiled yet"),
+                Statement::While { .. } => bail!("while loops cannot be compiled yet"),
+                Statement::For { .. } => bail!("for loops cannot be compiled yet"),
+                Statement::Assignment { .. } => bail!("assignments cannot be compiled yet"),
+            }
+        }
+
+        Ok(builder.graph)
+    }
+}
+
+impl GraphBuilder {
+    fn add_node(&mut self, node_type: &str, type_tag: &TypeTag, value: Option<String>, inputs: &[(&'static str, Wire)]) -> Result<Wire> {
+        let type_name = csharp_name(type_tag)
+            .ok_or_else(|| anyhow!("{type_tag} values cannot be compiled yet"))?;
+        let id = self.graph.nodes.len();
+        self.graph.nodes.push(GraphNode {
+            id,
+            node_type: format!("{node_type}<{type_name}>"),
+            name: None,
+            value,
+            inputs: inputs.iter()
+                .map(|(port, _)| GraphPort { name: port.to_string(), type_name: type_name.to_string() })
+                .collect(),
+            outputs: vec![GraphPort { name: "Result".to_string(), type_name: type_name.to_string() }],
+        });
+
+        for (port, wire) in inputs {
+            self.graph.connections.push(Connection {
+                from: Endpoint { node: wire.node, port: wire.port.to_string() },
+                to: Endpoint { node: id, port: port.to_string() },
+            });
+        }
+
+        Ok(Wire {
+            node: id,
+            port: "Result",
+            type_tag: type_tag.clone(),
+        })
+    }
+
+    /// adds a `ValueInput` which holds the literal. see [literal_type] for `expected`.
+    fn add_literal(&mut self, literal: &First, expected: Option<&TypeTag>) -> Result<Wire> {
+        let type_tag = literal_type(literal, expected, &self.default_int)?;
+        let value = match literal {
+            First::IntegralLiteral { sequence, .. } | First::FloatLiteral { sequence, .. } | First::StringLiteral { sequence, .. } => sequence.clone(),
+            First::True { .. } => "true".to_string(),
+            First::False { .. } => "false".to_string(),
+            other => unreachable!("it has been checked to be a literal, but it was {other:?}"),
+        };
+
+        self.add_node("ValueInput", &type_tag, Some(value), &[])
+    }
+
+    /// `A` and `B` must have the same type, which is the type of the result as well.
+    fn add_binary_node(&mut self, node_type: &str, lhs: Wire, rhs: Wire) -> Result<Wire> {
+        if lhs.type_tag != rhs.type_tag {
+            bail!("`{node_type}` needs operands of the same type, but they were {lhs} and {rhs}", lhs = lhs.type_tag, rhs = rhs.type_tag);
+        }
+
+        if !is_numeric(&lhs.type_tag) {
+            bail!("`{node_type}` on {type_tag} is not supported", type_tag = lhs.type_tag);
+        }
+
+        let type_tag = lhs.type_tag.clone();
+        self.add_node(node_type, &type_tag, None, &[("A", lhs), ("B", rhs)])
+    }
+}
+
+/// the spellings Resonite uses in the type argument of a node.
+static CSHARP_NAMES: [(TypeTag, &str); 12] = [
+    (TypeTag::I8, "sbyte"),
+    (TypeTag::U8, "byte"),
+    (TypeTag::I16, "short"),
+    (TypeTag::U16, "ushort"),
+    (TypeTag::I32, "int"),
+    (TypeTag::U32, "uint"),
+    (TypeTag::I64, "long"),
+    (TypeTag::U64, "ulong"),
+    (TypeTag::F32, "float"),
+    (TypeTag::F64, "double"),
+    (TypeTag::Bool, "bool"),
+    (TypeTag::String, "string"),
+];
+
+fn csharp_name(type_tag: &TypeTag) -> Option<&'static str> {
+    CSHARP_NAMES.iter()
+        .find(|(tag, _)| tag == type_tag)
+        .map(|(_, name)| *name)
+}
+
+/// the inverse of [csharp_name].
+pub(crate) fn from_csharp_name(name: &str) -> Option<TypeTag> {
+    CSHARP_NAMES.iter()
+        .find(|(_, spelling)| *spelling == name)
+        .map(|(tag, _)| tag.clone())
+}
+
+pub(crate) fn is_numeric(type_tag: &TypeTag) -> bool {
+    !matches!(type_tag, TypeTag::Bool | TypeTag::String)
+}
+
+/// the type of a literal. an unsuffixed literal takes `expected` if it is a numeric type, as `var a: i64 = 1` does,
+/// and otherwise an integral literal takes `default_int`. an integral literal must be in the range of the type.
+pub(crate) fn literal_type(literal: &First, expected: Option<&TypeTag>, default_int: &TypeTag) -> Result<TypeTag> {
+    let is_float = |type_tag: &TypeTag| matches!(type_tag, TypeTag::F32 | TypeTag::F64);
+    let type_tag = match (literal, expected) {
+        (First::IntegralLiteral { suffix: Some(suffix), .. }, _) => TypeTag::from_name(suffix).ok_or_else(|| anyhow!("unknown suffix: {suffix}"))?,
+        (First::IntegralLiteral { .. }, Some(expected)) if expected.is_integral() || is_float(expected) => expected.clone(),
+        (First::IntegralLiteral { .. }, _) => default_int.clone(),
+        (First::FloatLiteral { .. }, Some(expected)) if is_float(expected) => expected.clone(),
+        (First::FloatLiteral { .. }, _) => TypeTag::F32,
+        (First::StringLiteral { .. }, _) => TypeTag::String,
+        (First::True { .. } | First::False { .. }, _) => TypeTag::Bool,
+        (other, _) => unreachable!("only a literal has a literal type, but it was {other:?}"),
+    };
+
+    if let First::IntegralLiteral { sequence, .. } = literal {
+        if type_tag.is_integral() && !type_tag.can_hold(sequence) {
+            bail!("`{sequence}` is out of range for {type_tag}");
+        }
+    }
+
+    Ok(type_tag)
+}
+
+trait Lower {
+    /// `expected` is the type which the value is stored into, if it is known. see [literal_type].
+    /// the operands of an arithmetic node have the type of its result, so they expect the same type.
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire>;
+}
+
+impl Lower for First {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            // TODO: the spec says the type of an unsuffixed literal is decided by its context.
+            //       only the value of an annotated declaration is decided so far, and the others take the default type.
+            First::IntegralLiteral { .. } | First::FloatLiteral { .. } | First::StringLiteral { .. } | First::True { .. } | First::False { .. } => {
+                builder.add_literal(self, expected)
+            }
+            First::Variable { identifier } => builder.declared.get(identifier.as_str())
+                .cloned()
+                .ok_or_else(|| anyhow!("`{name}` is not declared", name = identifier.as_str())),
+            First::Parenthesized(inner) => inner.lower(builder, expected),
+            First::MemberPath { path } => bail!("{path} cannot be compiled yet, because members of nodes are not supported"),
+            First::Call { .. } => bail!("calls cannot be compiled yet"),
+            First::Match { .. } => bail!("match expressions cannot be compiled yet"),
+            First::List { .. } => bail!("lists cannot be compiled yet"),
+            First::Index { .. } => bail!("indexing cannot be compiled yet"),
+        }
+    }
+}
+
+impl Lower for Cast {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            Cast::Do { .. } => bail!("casts cannot be compiled yet"),
+            Cast::Propagated(first) => first.lower(builder, expected),
+        }
+    }
+}
+
+impl Lower for Unary {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            Unary::Do { .. } => bail!("unary operators cannot be compiled yet"),
+            Unary::Propagated(cast) => cast.lower(builder, expected),
+        }
+    }
+}
+
+impl Lower for Multiplicative {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            Multiplicative::Binary { operator, lhs, rhs } => {
+                let lhs = lhs.lower(builder, expected)?;
+                let rhs = rhs.lower(builder, expected)?;
+                let node_type = match operator {
+                    MultiplicativeOps::Multiply => "ValueMul",
+                    MultiplicativeOps::Divide => "ValueDiv",
+                    MultiplicativeOps::Reminder => "ValueMod",
+                };
+
+                builder.add_binary_node(node_type, lhs, rhs)
+            }
+            Multiplicative::Propagated(unary) => unary.lower(builder, expected),
+        }
+    }
+}
+
+impl Lower for Additive {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            Additive::Binary { operator, lhs, rhs } => {
+                let lhs = lhs.lower(builder, expected)?;
+                let rhs = rhs.lower(builder, expected)?;
+                let node_type = match operator {
+                    AdditiveOps::Add => "ValueAdd",
+                    AdditiveOps::Subtract => "ValueSub",
+                };
+
+                builder.add_binary_node(node_type, lhs, rhs)
+            }
+            Additive::Propagated(multiplicative) => multiplicative.lower(builder, expected),
+        }
+    }
+}
+
+macro_rules! not_lowered_yet {
+    ($name:ident, $description:literal) => {
+        impl Lower for $name {
+            fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+                match self {
+                    $name::Binary { .. } => bail!(concat!($description, " cannot be compiled yet")),
+                    $name::Propagated(inner) => inner.lower(builder, expected),
+                }
+            }
+        }
+    };
+}
+
+not_lowered_yet!(BitwiseShift, "bit shifts");
+not_lowered_yet!(RelationCheckExpression, "comparisons");
+not_lowered_yet!(EqualityCheckExpression, "equality checks");
+not_lowered_yet!(BitwiseAndExpression, "bitwise and");
+not_lowered_yet!(BitwiseXorExpression, "bitwise xor");
+not_lowered_yet!(BitwiseOrExpression, "bitwise or");
+not_lowered_yet!(LogicalAndExpression, "logical and");
+not_lowered_yet!(LogicalOrExpression, "logical or");
+
+impl Lower for Conditional {
+    fn lower(&self, builder: &mut GraphBuilder, expected: Option<&TypeTag>) -> Result<Wire> {
+        match self {
+            Conditional::Do { .. } => bail!("conditional expressions cannot be compiled yet"),
+            Conditional::Propagated(inner) => inner.lower(builder, expected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::graph::NodeGraph;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::resolved_type_tag::TypeTag;
+
+    fn compile(source: &str) -> anyhow::Result<NodeGraph> {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        NodeGraph::compile(&root, &TypeTag::I32)
+    }
+
+    fn node_types(graph: &NodeGraph) -> Vec<&str> {
+        graph.nodes.iter().map(|node| node.node_type.as_str()).collect()
+    }
+
+    #[test]
+    fn unsuffixed_literal_takes_the_declared_type() {
+        let graph = compile("var a: i64 = 1\nvar b: f64 = 2\nvar c: f32 = 0.5\n").expect("the literals fit in the declared types");
+
+        assert_eq!(node_types(&graph), ["ValueInput<long>", "ValueInput<double>", "ValueInput<float>"]);
+        assert_eq!(graph.nodes[0].value.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn unsuffixed_literal_in_an_operand_takes_the_declared_type() {
+        let graph = compile("var a: i64 = 1 + (2 * 3)\n").expect("the operands take the declared type");
+
+        assert_eq!(node_types(&graph), ["ValueInput<long>", "ValueInput<long>", "ValueInput<long>", "ValueMul<long>", "ValueAdd<long>"]);
+    }
+
+    #[test]
+    fn unsuffixed_literal_is_checked_against_the_declared_type() {
+        let error = compile("var a: u8 = 300\n").expect_err("300 does not fit in u8");
+
+        assert_eq!(error.to_string(), "`300` is out of range for u8");
+    }
+
+    #[test]
+    fn suffixed_literal_keeps_its_type() {
+        let error = compile("var a: i64 = 1i32\n").expect_err("a suffixed literal is not converted");
+
+        assert_eq!(error.to_string(), "`a` is declared as i64, but its value is i32");
+    }
+
+    #[test]
+    fn arithmetic_is_connected_to_its_operands() {
+        let graph = compile("var a = 1 + 2\nvar b = a * 3\n").expect("arithmetic can be compiled");
+
+        assert_eq!(node_types(&graph), ["ValueInput<int>", "ValueInput<int>", "ValueAdd<int>", "ValueInput<int>", "ValueMul<int>"]);
+        assert_eq!(graph.nodes[2].name.as_deref(), Some("a"));
+        assert_eq!(graph.connections.len(), 4);
+        assert_eq!(graph.connections[2].from.node, 2);
+        assert_eq!(graph.connections[2].to.node, 4);
+    }
+
+    #[test]
+    fn statement_without_a_node_is_rejected() {
+        let error = compile("if true then\nend\n").expect_err("if statements have no node yet");
+
+        assert_eq!(error.to_string(), "if statements cannot be compiled yet");
+    }
+}
@@ -1,47 +1,126 @@
+use std::borrow::Cow;
 use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
 
 use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use crate::compiler::span::Span;
 
-static KEYWORDS: [&str; 11] = ["if", "then", "else", "elseif", "end", "endif", "while", "wend", "for", "match", "as"];
+pub(crate) static KEYWORDS: [&str; 19] = ["if", "then", "else", "elseif", "end", "endif", "while", "wend", "for", "to", "match", "case", "as", "var", "const", "true", "false", "inf", "nan"];
 
-pub struct Lexer {
+/// type names which can follow the digits of an integral literal, such as `10u8`.
+static INTEGER_SUFFIXES: [&str; 8] = ["i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64"];
+
+pub struct Lexer<'a> {
+    /// counted in chars, which is the unit of every position the lexer reports.
     index: Cell<usize>,
-    current_source: String,
+    /// where `index` is in `current_source`, in bytes.
+    byte_index: Cell<usize>,
+    current_source: Cow<'a, str>,
+    /// the token which [Lexer::peek] scanned last. it is keyed by where the token starts,
+    /// so rewinding the lexer never makes it stale, and [LexerState] does not have to hold it.
+    lookahead: Cell<Option<Lookahead>>,
+    /// how many times [Lexer::scan] ran, so that tests can tell whether the lookahead was reused.
+    #[cfg(test)]
+    scans: Cell<usize>,
+}
+
+struct Lookahead {
+    start: usize,
+    end: LexerState,
+    token: Result<Token, LexError>,
+}
+
+/// everything which [Lexer::next] mutates. [Lexer::peek] relies on this to undo `next`,
+/// so any mutable state added to [Lexer] must be added here too.
+/// outside of the lexer, this is only a position returned by [Lexer::mark].
+#[derive(Copy, Clone)]
+pub struct LexerState {
+    index: usize,
+    byte_index: usize,
 }
 
-impl Lexer {
+impl Lexer<'static> {
+    /// copies the source into the lexer. use [Lexer::from_borrowed] if the source outlives the lexer.
     pub fn create(source: &str) -> Self {
+        Self::with_source(Cow::Owned(source.to_string()))
+    }
+
+    /// reads the whole source from `reader` into the lexer's own buffer.
+    /// unlike [Lexer::create], the source is not held twice, which matters for large exports.
+    pub fn from_reader<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut current_source = String::new();
+        reader.read_to_string(&mut current_source)?;
+
+        Ok(Self::with_source(Cow::Owned(current_source)))
+    }
+}
+
+impl<'a> Lexer<'a> {
+    /// reads the source in place, without copying it.
+    pub fn from_borrowed(source: &'a str) -> Self {
+        Self::with_source(Cow::Borrowed(source))
+    }
+
+    /// the whole source, regardless of how much has been read.
+    pub fn source(&self) -> &str {
+        &self.current_source
+    }
+
+    fn with_source(current_source: Cow<'a, str>) -> Self {
         Self {
-            current_source: source.to_string(),
             index: Cell::new(0),
+            byte_index: Cell::new(0),
+            current_source,
+            lookahead: Cell::new(None),
+            #[cfg(test)]
+            scans: Cell::new(0),
         }
     }
 
     fn drain_space(&self) {
-        while !self.reached_end() && self.current_char().expect("oops") == ' ' {
-            self.index.set(self.index.get() + 1);
+        while self.current_char_is(' ') || self.current_char_is('\t') {
+            self.advance();
         }
     }
 
-    pub fn next(&self) -> Token {
+    pub fn next(&self) -> Result<Token, LexError> {
         self.drain_space();
+        let start = self.index.get();
+        if let Some(lookahead) = self.lookahead.take().filter(|lookahead| lookahead.start == start) {
+            self.restore(lookahead.end);
+            return lookahead.token
+        }
+
+        self.scan().map_err(|reason| LexError {
+            index: start,
+            kind: reason.downcast().unwrap_or_else(|other| LexErrorKind::Malformed(other.to_string())),
+        })
+    }
 
+    fn scan(&self) -> Result<Token> {
+        #[cfg(test)]
+        self.scans.set(self.scans.get() + 1);
         if self.reached_end() {
-            return Token::EndOfFile
+            return Ok(Token::EndOfFile)
         }
 
-        let c = self.current_char().expect("oops");
-        match c {
+        let c = self.current_char()?;
+        let token = match c {
             '\n' => {
                 self.advance();
                 Token::NewLine
             },
+            // a lone `\r` is not a line ending
+            '\r' if self.consume_str("\r\n") => Token::NewLine,
             '=' => {
-                self.advance();
-                if self.current_char().expect("oops") == '=' {
-                    self.advance();
+                if self.consume_str("==") {
                     Token::PartEqEq
+                } else if self.consume_str("=>") {
+                    Token::PartEqMore
                 } else {
+                    self.advance();
                     Token::SymEq
                 }
             },
@@ -59,15 +138,23 @@ impl Lexer {
             },
             '/' => {
                 self.advance();
-                if self.current_char().expect("oops") == '/' {
+                if self.current_char_is('/') {
                     self.advance();
                     Token::Comment {
-                        content: self.scan_comment_content().expect("failed to process")
+                        content: self.scan_comment_content()?
                     }
                 } else {
                     Token::SymSlash
                 }
             },
+            '~' => {
+                self.advance();
+                Token::SymTilde
+            },
+            '%' => {
+                self.advance();
+                Token::SymPercent
+            },
             '(' => {
                 self.advance();
                 Token::SymLeftPar
@@ -76,6 +163,10 @@ impl Lexer {
                 self.advance();
                 Token::SymRightPar
             },
+            ',' => {
+                self.advance();
+                Token::SymComma
+            },
             '#' => {
                 self.advance();
                 Token::SymSharp
@@ -92,64 +183,100 @@ impl Lexer {
                 self.advance();
                 Token::SymColon
             },
+            '?' => {
+                self.advance();
+                Token::SymQuestion
+            },
             '.' => {
                 self.advance();
                 Token::SymDot
             },
+            // the longest operator is tried first, so that `<=>` is not read as `<=` and `>`
             '<' => {
-                self.advance();
-                if self.current_char().expect("oops") == '=' {
-                    self.advance();
-                    if self.current_char().expect("oops") == '>' {
-                        self.advance();
-                        Token::PartLessEqMore
-                    } else {
-                        Token::PartLessEq
-                    }
-                } else if self.current_char().expect("oops") == '<' {
+                if self.consume_str("<=>") {
+                    Token::PartLessEqMore
+                } else if self.consume_str("<=") {
+                    Token::PartLessEq
+                } else if self.consume_str("<<") {
                     Token::PartLessLess
                 } else {
+                    self.advance();
                     Token::SymLess
                 }
             },
             '>' => {
-                self.advance();
-                if self.current_char().expect("oops") == '=' {
-                    self.advance();
+                if self.consume_str(">=") {
                     Token::PartMoreEq
-                } else if self.current_char().expect("oops") == '>' {
+                } else if self.consume_str(">>") {
                     Token::PartMoreMore
                 } else {
+                    self.advance();
                     Token::SymMore
                 }
             },
             '!' => {
-                self.advance();
-                if self.current_char().expect("oops") == '=' {
-                    self.advance();
+                if self.consume_str("!=") {
                     Token::PartBangEq
                 } else {
+                    self.advance();
                     Token::SymBang
                 }
             },
+            '&' => {
+                if self.consume_str("&&") {
+                    Token::PartAndAnd
+                } else {
+                    self.advance();
+                    Token::SymAnd
+                }
+            },
+            '|' => {
+                if self.consume_str("||") {
+                    Token::PartPipePipe
+                } else {
+                    self.advance();
+                    Token::SymPipe
+                }
+            },
+            '^' => {
+                self.advance();
+                Token::SymCaret
+            },
             '"' => {
                 self.advance();
-                let scanned_content = self.scan_string_literal().unwrap();
-                assert_eq!(self.consume_char().unwrap(), '"', "The string literal is not terminated");
+                let scanned_content = self.scan_string_literal()?;
+                // scan_string_literal stops right before the closing quote
+                self.advance();
                 Token::StringLiteral {
                     content: scanned_content,
                 }
             },
-            c if c.is_ascii_digit() => self.scan_digits().expect("oops"),
-            c if c.is_ascii_lowercase() => {
-                let scan_result = self.scan_lowers().expect("oops");
+            c if c.is_ascii_digit() => self.scan_digits()?,
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let scan_result = self.scan_identifier()?;
                 let is_keyword = KEYWORDS.contains(&scan_result.as_str());
                 if is_keyword {
                     match scan_result.as_str() {
                         "var" => Token::VarKeyword,
+                        "const" => Token::KeywordConst,
                         "true" => Token::KeywordTrue,
                         "false" => Token::KeywordFalse,
                         "as" => Token::KeywordAs,
+                        "if" => Token::KeywordIf,
+                        "then" => Token::KeywordThen,
+                        "else" => Token::KeywordElse,
+                        "elseif" => Token::KeywordElseIf,
+                        "end" => Token::KeywordEnd,
+                        "while" => Token::KeywordWhile,
+                        "wend" => Token::KeywordWend,
+                        "for" => Token::KeywordFor,
+                        "to" => Token::KeywordTo,
+                        "match" => Token::KeywordMatch,
+                        "case" => Token::KeywordCase,
+                        // infinity and NaN, which cannot be written with digits
+                        "inf" | "nan" => Token::FloatLiteral {
+                            sequence: scan_result,
+                        },
                         other => Token::Reserved {
                             matched: other.to_string(),
                         }
@@ -158,14 +285,99 @@ impl Lexer {
                     Token::Identifier { inner: scan_result }
                 }
             },
-            other => Token::UnexpectedChar {
-                index: self.index.get(),
-                char: other,
+            other => bail!(LexErrorKind::UnexpectedChar(other)),
+        };
+
+        Ok(token)
+    }
+
+    fn scan_digits(&self) -> Result<Token> {
+        let mut buf = self.scan_digit_sequence()?;
+        if buf == "0" && !self.reached_end() && matches!(self.current_char()?, 'x' | 'X') {
+            return self.scan_hex_digits()
+        }
+        let mut is_float = false;
+
+        // `1.5` is a float, but `1.foo` and `1.` are an integer followed by a dot
+        let is_fraction = !self.reached_end()
+            && self.current_char()? == '.'
+            && self.char_after_current().is_some_and(|c| c.is_ascii_digit());
+        if is_fraction {
+            buf.push(self.consume_char()?);
+            buf.push_str(&self.scan_digit_sequence()?);
+            is_float = true;
+        }
+
+        if !self.reached_end() && matches!(self.current_char()?, 'e' | 'E') {
+            buf.push(self.consume_char()?);
+            // the sign is only allowed right after the exponent marker
+            if !self.reached_end() && matches!(self.current_char()?, '+' | '-') {
+                buf.push(self.consume_char()?);
+            }
+            let exponent = self.scan_digit_sequence()?;
+            if exponent.is_empty() {
+                bail!("invalid number literal: exponent has no digits")
             }
+            buf.push_str(&exponent);
+            is_float = true;
+        }
+
+        let suffix = self.scan_integer_suffix(is_float)?;
+        if is_float {
+            Ok(Token::FloatLiteral {
+                sequence: buf
+            })
+        } else {
+            Ok(Token::Digits {
+                sequence: buf,
+                suffix,
+            })
         }
     }
 
-    fn scan_digits(&self) -> Result<Token> {
+    /// `0xF0` is written as `240` in [Token::Digits], so that the rest of the compiler only sees decimal digits.
+    /// the leading `0` must be consumed by the caller.
+    fn scan_hex_digits(&self) -> Result<Token> {
+        // `x` or `X`
+        self.consume_char()?;
+        let mut digits = String::new();
+        while !self.reached_end() && self.current_char()?.is_ascii_hexdigit() {
+            digits.push(self.consume_char()?);
+        }
+        if digits.is_empty() {
+            bail!("invalid number literal: `0x` has no digits")
+        }
+
+        let value = u128::from_str_radix(&digits, 16)
+            .map_err(|_| anyhow!("invalid number literal: `0x{digits}` is too large"))?;
+
+        Ok(Token::Digits {
+            sequence: value.to_string(),
+            suffix: self.scan_integer_suffix(false)?,
+        })
+    }
+
+    /// the type name right after the digits, such as `u8` in `10u8`. a float literal cannot have one.
+    fn scan_integer_suffix(&self, is_float: bool) -> Result<Option<String>> {
+        if self.reached_end() {
+            return Ok(None)
+        }
+
+        let c = self.current_char()?;
+        if !(c.is_alphabetic() || c == '_') {
+            return Ok(None)
+        }
+
+        let word = self.scan_identifier()?;
+        // `123abc` is almost certainly a typo, so reject it instead of splitting it into two tokens
+        if is_float || !INTEGER_SUFFIXES.contains(&word.as_str()) {
+            bail!("invalid number literal: unexpected `{c}` after digits")
+        }
+
+        Ok(Some(word))
+    }
+
+    fn scan_digit_sequence(&self) -> Result<String> {
         let mut buf = String::new();
         loop {
             if self.reached_end() {
@@ -174,7 +386,7 @@ impl Lexer {
 
             // DON'T CONSUME!!
             let c = self.current_char()?;
-            if c.is_ascii_digit() {
+            if !c.is_ascii_digit() {
                 break
             }
             let c = self.consume_char()?;
@@ -182,12 +394,11 @@ impl Lexer {
             buf.push(c);
         }
 
-        Ok(Token::Digits {
-            sequence: buf
-        })
+        Ok(buf)
     }
 
-    fn scan_lowers(&self) -> Result<String> {
+    /// identifiers may contain digits after the first char, e.g. `i32` or `float3`.
+    fn scan_identifier(&self) -> Result<String> {
         let mut buf = String::new();
         loop {
             if self.reached_end() {
@@ -196,7 +407,7 @@ impl Lexer {
 
             // DON'T CONSUME!!
             let c = self.current_char()?;
-            if c.is_ascii_lowercase() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
                 break
             }
             let c = self.consume_char()?;
@@ -209,19 +420,23 @@ impl Lexer {
 
     fn scan_comment_content(&self) -> Result<String> {
         let mut buf = String::new();
-        while !self.reached_end() && self.current_char().expect("oops") != '\n' {
+        while !self.reached_end() && !self.current_char_is('\n') && !self.rest().starts_with("\r\n") {
             buf.push(self.consume_char()?)
         }
         Ok(buf)
     }
 
+    /// the opening quote must be consumed by the caller.
     fn scan_string_literal(&self) -> Result<String> {
-        self.advance();
         let mut buf = String::new();
         let mut in_escape = false;
         loop {
+            if self.reached_end() {
+                bail!(LexErrorKind::UnterminatedString)
+            }
+
             if in_escape {
-                match self.current_char().expect("oops") {
+                match self.current_char()? {
                     '"' => {
                         buf.push('"');
                         self.consume_char()?;
@@ -281,8 +496,10 @@ impl Lexer {
                         // TODO: support unicode codepoint sequence: \u{ 3015 3028 3033 }
                         let mut codepoint: u16 = 0;
                         for _ in 0..=3 {
-                            let or_value = match self.current_char()
-                                .expect("There must be more characters to recognize Unicode escape sequence") {
+                            if self.reached_end() {
+                                bail!("There must be more characters to recognize Unicode escape sequence")
+                            }
+                            let or_value = match self.current_char()? {
                                 '0' => 0,
                                 '1' => 1,
                                 '2' => 2,
@@ -300,10 +517,10 @@ impl Lexer {
                                 'E' | 'e' => 0xE,
                                 'F' | 'f' => 0xF,
                                 '"' => bail!("An Unicode escape sequence must have four hexadecimal codepoint, but there's no codepoint anymore."),
-                                other_char => bail!("An Unicode escape sequence must have four hexadecimal codepoint, but there's other character that is not valid a codepoint character."),
+                                other_char => bail!("An Unicode escape sequence must have four hexadecimal codepoint, but there's other character ({other_char}) that is not valid a codepoint character."),
                             };
                             codepoint = (codepoint << 4) | or_value;
-                            self.consume_char().unwrap();
+                            self.consume_char()?;
                         }
                         let surrogate_code_point_range = 0xD800..=0xDFFF;
                         if surrogate_code_point_range.contains(&codepoint) {
@@ -320,14 +537,18 @@ impl Lexer {
                     }
                 }
             } else {
-                match self.current_char().expect("oops") {
+                match self.current_char()? {
                     '"' => break,
                     '\\' => {
+                        self.advance();
                         in_escape = true;
                         continue
                     }
                     '\n' => bail!("String literal can not contain newline literally. To script newline, please escape as \"\\n\"."),
-                    other_char => buf.push(other_char),
+                    other_char => {
+                        self.advance();
+                        buf.push(other_char)
+                    }
                 }
             }
         }
@@ -335,26 +556,119 @@ impl Lexer {
         Ok(buf)
     }
 
-    pub fn peek(&self) -> Token {
-        let current_index = self.index.get();
+    /// same as [Self::next], but also returns where the token is in the source.
+    pub fn next_spanned(&self) -> Result<(Token, Span), LexError> {
+        self.drain_space();
+        let start = self.index.get();
+        let token = self.next()?;
+        Ok((token, Span { start, end: self.index.get() }))
+    }
+
+    /// same as [Self::next_spanned], but skips comments and newlines, which are trivia where lines do not matter,
+    /// such as between the arms of `match`. elsewhere the parser uses [Self::next] since a newline terminates a statement.
+    pub fn next_significant(&self) -> Result<(Token, Span), LexError> {
+        loop {
+            match self.next_spanned()? {
+                (Token::Comment { .. } | Token::NewLine, _) => {}
+                significant => return Ok(significant),
+            }
+        }
+    }
+
+    /// returns the token which the next call of [Lexer::next] returns, without consuming it.
+    /// calling this any number of times in a row is the same as calling it once,
+    /// and the token is scanned only once, even by the following `next`.
+    pub fn peek(&self) -> Result<Token, LexError> {
+        self.scan_lookahead().0
+    }
+
+    /// same as [Self::peek], but also returns where the token is in the source.
+    pub fn peek_spanned(&self) -> Result<(Token, Span), LexError> {
+        let (token, span) = self.scan_lookahead();
+        token.map(|token| (token, span))
+    }
+
+    /// scans the next token without consuming it, and keeps it for the following [Lexer::peek] and [Lexer::next].
+    fn scan_lookahead(&self) -> (Result<Token, LexError>, Span) {
+        let state = self.save();
+        self.drain_space();
+        let start = self.index.get();
         let token = self.next();
-        self.index.set(current_index);
+        let end = self.save();
+        self.lookahead.set(Some(Lookahead {
+            start,
+            end,
+            token: token.clone(),
+        }));
+        self.restore(state);
+
+        (token, Span { start, end: end.index })
+    }
+
+    /// returns the token which the `n + 1`-th call of [Lexer::next] returns, without consuming anything.
+    /// `peek_nth(0)` is the same as [Lexer::peek].
+    pub fn peek_nth(&self, n: usize) -> Result<Token, LexError> {
+        let state = self.save();
+        let token = (0..n).try_for_each(|_| self.next().map(drop)).and_then(|_| self.next());
+        self.restore(state);
         token
     }
 
+    /// returns the current position, so that [Lexer::reset] can rewind to it.
+    pub fn mark(&self) -> LexerState {
+        self.save()
+    }
+
+    /// rewinds to the position which [Lexer::mark] returned.
+    pub fn reset(&self, mark: LexerState) {
+        self.restore(mark);
+    }
+
+    /// skips a char, even if it cannot start a token. this is for recovering from a malformed token.
+    pub fn skip_char(&self) {
+        if !self.reached_end() {
+            self.advance();
+        }
+    }
+
+    fn save(&self) -> LexerState {
+        LexerState {
+            index: self.index.get(),
+            byte_index: self.byte_index.get(),
+        }
+    }
+
+    fn restore(&self, state: LexerState) {
+        self.index.set(state.index);
+        self.byte_index.set(state.byte_index);
+    }
+
+    /// the source from the current char to the end.
+    fn rest(&self) -> &str {
+        &self.current_source[self.byte_index.get()..]
+    }
+
     fn current_char(&self) -> Result<char> {
-        self.current_source
-            .as_str()
+        self.rest()
             .chars()
-            .nth(self.index.get())
+            .next()
             .ok_or_else(||
                 anyhow!("index: out of range (idx={request}, max={max})",
                     request = self.index.get(),
-                    max = self.current_source.len()
+                    max = self.current_source.chars().count()
                 )
             )
     }
 
+    /// returns `false` at the end of the source.
+    fn current_char_is(&self, expected: char) -> bool {
+        !self.reached_end() && self.current_char().is_ok_and(|c| c == expected)
+    }
+
+    fn char_after_current(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
     fn consume_char(&self) -> Result<char> {
         let c = self.current_char()?;
         self.advance();
@@ -362,29 +676,86 @@ impl Lexer {
     }
 
     fn reached_end(&self) -> bool {
-        self.index.get() >= self.current_source.len()
+        self.byte_index.get() >= self.current_source.len()
     }
 
     fn advance(&self) {
         self.advance_by(1);
     }
 
+    /// advances past `expected` if the source continues with it. otherwise, does nothing.
+    fn consume_str(&self, expected: &str) -> bool {
+        let matches = self.rest().starts_with(expected);
+        if matches {
+            self.advance_by(expected.chars().count());
+        }
+
+        matches
+    }
+
+    /// `step` is in chars. advancing past the end of the source stops at the end.
     fn advance_by(&self, step: usize) {
-        self.index.set(self.index.get() + step);
+        let (chars, bytes) = self.rest()
+            .chars()
+            .take(step)
+            .fold((0, 0), |(chars, bytes), c| (chars + 1, bytes + c.len_utf8()));
+        self.index.set(self.index.get() + chars);
+        self.byte_index.set(self.byte_index.get() + bytes);
+    }
+}
+
+/// a malformed token, such as an unterminated string literal.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LexError {
+    /// where the malformed token starts, in chars.
+    pub index: usize,
+    pub kind: LexErrorKind,
+}
+
+/// only what is wrong. [Diagnostic](crate::compiler::diagnostic::Diagnostic) tells where it is, in lines and columns.
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{kind}", kind = self.kind)
     }
 }
 
+impl std::error::Error for LexError {}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    /// the char cannot start any token, such as `$`.
+    UnexpectedChar(char),
+    /// anything else, described by the message.
+    Malformed(String),
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::UnterminatedString => write!(f, "the string literal is not terminated"),
+            LexErrorKind::UnexpectedChar(c) => write!(f, "`{c}` is not a valid character"),
+            LexErrorKind::Malformed(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for LexErrorKind {}
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
 pub enum Token {
     Identifier {
         inner: String,
     },
     Digits {
+        /// always in decimal, even if the source is written in hexadecimal such as `0xF0`
         sequence: String,
+        /// the type name written right after the digits, such as `u8` in `10u8`
+        suffix: Option<String>,
     },
-    UnexpectedChar {
-        index: usize,
-        char: char,
+    /// `1.5`, `1e-5`, `2.5E+3`, `inf` or `nan`
+    FloatLiteral {
+        sequence: String,
     },
     Comment {
         content: String,
@@ -397,9 +768,22 @@ pub enum Token {
     NewLine,
     /// `"var"`
     VarKeyword,
+    /// `"const"`
+    KeywordConst,
     KeywordTrue,
     KeywordFalse,
     KeywordAs,
+    KeywordIf,
+    KeywordThen,
+    KeywordElse,
+    KeywordElseIf,
+    KeywordEnd,
+    KeywordWhile,
+    KeywordWend,
+    KeywordFor,
+    KeywordTo,
+    KeywordMatch,
+    KeywordCase,
     /// `"="`
     SymEq,
     /// `"+"`
@@ -410,10 +794,14 @@ pub enum Token {
     SymAsterisk,
     /// `/`
     SymSlash,
+    /// `%`
+    SymPercent,
     /// `"("`
     SymLeftPar,
     /// `")"`
     SymRightPar,
+    /// `,`
+    SymComma,
     /// `>`
     SymMore,
     /// `>>`
@@ -434,8 +822,12 @@ pub enum Token {
     PartPipePipe,
     /// `!`
     SymBang,
+    /// `~`
+    SymTilde,
     /// `==`
     PartEqEq,
+    /// `=>`
+    PartEqMore,
     /// `!=`
     PartBangEq,
     /// `<=`
@@ -452,11 +844,304 @@ pub enum Token {
     SymCloseBracket,
     /// `:`
     SymColon,
+    /// `?`
+    SymQuestion,
     /// `.`
     SymDot,
     /// reserved for future use.
     Reserved {
         matched: String,
     },
+}
+impl Token {
+    /// returns the token spelled as `operator`, if it is an operator, a punctuation or a keyword.
+    pub fn from_operator(operator: &str) -> Option<Token> {
+        let token = match operator {
+            "var" => Token::VarKeyword,
+            "const" => Token::KeywordConst,
+            "true" => Token::KeywordTrue,
+            "false" => Token::KeywordFalse,
+            "as" => Token::KeywordAs,
+            "if" => Token::KeywordIf,
+            "then" => Token::KeywordThen,
+            "else" => Token::KeywordElse,
+            "elseif" => Token::KeywordElseIf,
+            "end" => Token::KeywordEnd,
+            "while" => Token::KeywordWhile,
+            "wend" => Token::KeywordWend,
+            "for" => Token::KeywordFor,
+            "to" => Token::KeywordTo,
+            "match" => Token::KeywordMatch,
+            "case" => Token::KeywordCase,
+            "=" => Token::SymEq,
+            "+" => Token::SymPlus,
+            "-" => Token::SymMinus,
+            "*" => Token::SymAsterisk,
+            "/" => Token::SymSlash,
+            "%" => Token::SymPercent,
+            "(" => Token::SymLeftPar,
+            ")" => Token::SymRightPar,
+            "," => Token::SymComma,
+            ">" => Token::SymMore,
+            ">>" => Token::PartMoreMore,
+            "<" => Token::SymLess,
+            "<<" => Token::PartLessLess,
+            "&" => Token::SymAnd,
+            "&&" => Token::PartAndAnd,
+            "^" => Token::SymCaret,
+            "|" => Token::SymPipe,
+            "||" => Token::PartPipePipe,
+            "!" => Token::SymBang,
+            "~" => Token::SymTilde,
+            "==" => Token::PartEqEq,
+            "=>" => Token::PartEqMore,
+            "!=" => Token::PartBangEq,
+            "<=" => Token::PartLessEq,
+            ">=" => Token::PartMoreEq,
+            "<=>" => Token::PartLessEqMore,
+            "#" => Token::SymSharp,
+            "[" => Token::SymOpenBracket,
+            "]" => Token::SymCloseBracket,
+            ":" => Token::SymColon,
+            "?" => Token::SymQuestion,
+            "." => Token::SymDot,
+            _ => return None,
+        };
+
+        Some(token)
+    }
+}
+
+impl TryFrom<&str> for Token {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Token::from_operator(value).ok_or_else(|| anyhow!("{value} is not an operator nor a keyword"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::{LexError, LexErrorKind, Lexer, Token};
+    use crate::compiler::span::Span;
+
+    fn tokens(lexer: &Lexer) -> Vec<(Token, Span)> {
+        let mut tokens = vec![];
+        loop {
+            let (token, span) = lexer.next_spanned().expect("the source can be lexed");
+            if token == Token::EndOfFile {
+                return tokens
+            }
+            tokens.push((token, span));
+        }
+    }
+
+    fn kinds(source: &str) -> Vec<Token> {
+        tokens(&Lexer::from_borrowed(source)).into_iter().map(|(token, _)| token).collect()
+    }
+
+    #[test]
+    fn crlf_and_tabs_are_lexed_as_lf_and_spaces() {
+        assert_eq!(kinds("var a = 1\r\n\tvar b = 2 // c\r\n"), kinds("var a = 1\n    var b = 2 // c\n"));
+        assert_eq!(tokens(&Lexer::from_borrowed("a\r\nb"))[1], (Token::NewLine, Span { start: 1, end: 3 }));
+    }
+
+    #[test]
+    fn lone_carriage_return_is_rejected() {
+        assert!(Lexer::from_borrowed("\rb").next().is_err());
+    }
+
+    #[test]
+    fn letter_right_after_digits_is_rejected() {
+        let error = Lexer::from_borrowed("123abc").next().expect_err("`abc` is not a suffix");
+
+        assert_eq!(error.to_string(), "invalid number literal: unexpected `a` after digits");
+        assert_eq!(
+            kinds("123 abc"),
+            [Token::Digits { sequence: "123".to_string(), suffix: None }, Token::Identifier { inner: "abc".to_string() }]
+        );
+    }
+
+    #[test]
+    fn exponent_may_have_a_sign() {
+        let float = |sequence: &str| Token::FloatLiteral { sequence: sequence.to_string() };
+
+        assert_eq!(kinds("1e-5"), [float("1e-5")]);
+        assert_eq!(kinds("2.5E+3"), [float("2.5E+3")]);
+        assert_eq!(kinds("1.0e-5 - 1"), [float("1.0e-5"), Token::SymMinus, Token::Digits { sequence: "1".to_string(), suffix: None }]);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn exponent_without_digits_is_rejected() {
+        for source in ["1.0e", "1e+", "1e-x"] {
+            let error = Lexer::from_borrowed(source).next().expect_err("the exponent has no digits");
+            assert_eq!(error.to_string(), "invalid number literal: exponent has no digits", "{source}");
+        }
+    }
+
+    #[test]
+    fn dot_after_digits_continues_a_float_only_before_a_digit() {
+        let int = Token::Digits { sequence: "1".to_string(), suffix: None };
+
+        assert_eq!(kinds("1.5"), [Token::FloatLiteral { sequence: "1.5".to_string() }]);
+        assert_eq!(kinds("1.foo"), [int.clone(), Token::SymDot, Token::Identifier { inner: "foo".to_string() }]);
+        assert_eq!(kinds("1."), [int, Token::SymDot]);
+    }
+
+    #[test]
+    fn percent_is_lexed() {
+        let digits = |sequence: &str| Token::Digits { sequence: sequence.to_string(), suffix: None };
+
+        assert_eq!(kinds("7 % 3"), [digits("7"), Token::SymPercent, digits("3")]);
+    }
+
+    #[test]
+    fn operator_spelling_maps_to_its_token() {
+        assert_eq!(Token::from_operator("<=>"), Some(Token::PartLessEqMore));
+        assert_eq!(Token::from_operator("xyz"), None);
+        assert_eq!(Token::try_from("xyz").expect_err("xyz is not an operator").to_string(), "xyz is not an operator nor a keyword");
+    }
+
+    #[test]
+    fn operator_spelling_lexes_to_the_same_token() {
+        for spelling in ["<=", "<=>", "==", "!=", "&&", "||", "<<", ">>", "=>", "%", "~", "^", "var", "const", "true", "match"] {
+            let token = Token::from_operator(spelling).expect("it is an operator or a keyword");
+            assert_eq!(kinds(spelling), [token], "{spelling}");
+        }
+    }
+
+    #[test]
+    fn peeking_any_number_of_times_does_not_move_the_lexer() {
+        let lexer = Lexer::from_borrowed("var a = 1");
+        for _ in 0..100 {
+            assert_eq!(lexer.peek().expect("the source can be lexed"), Token::VarKeyword);
+        }
+
+        assert_eq!(lexer.next_spanned().expect("the source can be lexed"), (Token::VarKeyword, Span { start: 0, end: 3 }));
+        assert_eq!(lexer.peek_nth(1).expect("the source can be lexed"), Token::SymEq);
+        assert_eq!(lexer.next().expect("the source can be lexed"), Token::Identifier { inner: "a".to_string() });
+    }
+
+    #[test]
+    fn repeated_peeks_scan_the_token_once() {
+        let lexer = Lexer::from_borrowed("var a = 1");
+        for _ in 0..100 {
+            lexer.peek().expect("the source can be lexed");
+        }
+        assert_eq!(lexer.scans.get(), 1);
+
+        lexer.next().expect("the source can be lexed");
+        assert_eq!(lexer.scans.get(), 1, "next should take the peeked token");
+
+        lexer.peek().expect("the source can be lexed");
+        lexer.peek_spanned().expect("the source can be lexed");
+        assert_eq!(lexer.scans.get(), 2);
+    }
+
+    #[test]
+    fn source_ending_in_the_middle_of_a_token_is_an_error_instead_of_a_panic() {
+        // the first char of a multi-char operator is a token by itself
+        for operator in ["=", "<", ">", "!", "&", "|", "/"] {
+            assert!(!kinds(operator).is_empty(), "{operator}");
+        }
+
+        assert_eq!(Lexer::from_borrowed("a = \"abc").peek_nth(2), Err(LexError { index: 4, kind: LexErrorKind::UnterminatedString }));
+        assert!(Lexer::from_borrowed("\"a\\").next().is_err());
+    }
+
+    #[test]
+    fn reset_rewinds_to_the_mark() {
+        let lexer = Lexer::from_borrowed("a.b = 1");
+        lexer.next().expect("the source can be lexed");
+        let mark = lexer.mark();
+        let rest = tokens(&lexer);
+
+        lexer.reset(mark);
+        assert_eq!(tokens(&lexer), rest);
+    }
+
+    #[test]
+    fn positions_after_non_ascii_text_are_counted_in_chars() {
+        let lexer = Lexer::from_borrowed("\"héllo\" // ÿ\nabc");
+
+        assert_eq!(tokens(&lexer), [
+            (Token::StringLiteral { content: "héllo".to_string() }, Span { start: 0, end: 7 }),
+            (Token::Comment { content: " ÿ".to_string() }, Span { start: 8, end: 12 }),
+            (Token::NewLine, Span { start: 12, end: 13 }),
+            (Token::Identifier { inner: "abc".to_string() }, Span { start: 13, end: 16 }),
+        ]);
+    }
+
+    #[test]
+    fn consume_str_advances_only_past_a_match() {
+        let lexer = Lexer::from_borrowed("é<=>");
+
+        assert!(!lexer.consume_str("<="));
+        assert!(lexer.consume_str("é"));
+        assert!(lexer.consume_str("<="));
+        assert_eq!(lexer.next_spanned().expect("the source can be lexed"), (Token::SymMore, Span { start: 3, end: 4 }));
+    }
+
+    #[test]
+    fn logical_and_bitwise_operators_are_lexed() {
+        assert_eq!(
+            kinds("&& & || | ^"),
+            [Token::PartAndAnd, Token::SymAnd, Token::PartPipePipe, Token::SymPipe, Token::SymCaret]
+        );
+        assert_eq!(kinds("a&&b"), kinds("a && b"));
+        assert_eq!(kinds("a&b"), kinds("a & b"));
+    }
+
+    #[test]
+    fn tilde_is_lexed_before_an_identifier() {
+        assert_eq!(kinds("~x"), [Token::SymTilde, Token::Identifier { inner: "x".to_string() }]);
+    }
+
+    #[test]
+    fn borrowed_source_is_not_copied() {
+        let source = String::from("var a = 1");
+        let lexer = Lexer::from_borrowed(&source);
+
+        assert!(std::ptr::eq(lexer.source(), source.as_str()));
+        assert!(!std::ptr::eq(Lexer::create(&source).source(), source.as_str()));
+    }
+
+    #[test]
+    fn char_which_cannot_start_a_token_is_an_error() {
+        let lexer = Lexer::from_borrowed("a $ b");
+        lexer.next().expect("`a` is an identifier");
+
+        assert_eq!(lexer.next(), Err(LexError { index: 2, kind: LexErrorKind::UnexpectedChar('$') }));
+    }
+
+    #[test]
+    fn reader_and_borrowed_source_give_the_same_tokens() {
+        let source = (0..2000).map(|i| format!("var a{i} = {i} + (\"{i}\" == \"x\")\n")).collect::<String>();
+        let from_reader = Lexer::from_reader(source.as_bytes()).expect("reading from a slice never fails");
+
+        assert_eq!(tokens(&from_reader), tokens(&Lexer::from_borrowed(&source)));
+    }
+
+    #[test]
+    fn hexadecimal_literal_is_normalized_to_decimal() {
+        let digits = |sequence: &str, suffix: Option<&str>| Token::Digits { sequence: sequence.to_string(), suffix: suffix.map(str::to_string) };
+
+        assert_eq!(kinds("0xF0 & 0x0f"), [digits("240", None), Token::SymAnd, digits("15", None)]);
+        assert_eq!(kinds("0XFFu8"), [digits("255", Some("u8"))]);
+        assert_eq!(tokens(&Lexer::from_borrowed("0xF0")), [(digits("240", None), Span { start: 0, end: 4 })]);
+    }
+
+    #[test]
+    fn malformed_hexadecimal_literal_is_rejected() {
+        assert_eq!(Lexer::from_borrowed("0x").next().expect_err("no digits").to_string(), "invalid number literal: `0x` has no digits");
+        assert_eq!(Lexer::from_borrowed("0xFG").next().expect_err("G is not hexadecimal").to_string(), "invalid number literal: unexpected `G` after digits");
+    }
+
+    #[test]
+    fn next_significant_skips_comments_and_newlines() {
+        let lexer = Lexer::from_borrowed("// x\n\nvar a = 1");
+
+        assert_eq!(lexer.next_significant().expect("the source can be lexed"), (Token::VarKeyword, Span { start: 6, end: 9 }));
+        assert_eq!(lexer.next().expect("the source can be lexed"), Token::Identifier { inner: "a".to_string() });
+    }
+}
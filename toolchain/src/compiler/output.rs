@@ -0,0 +1,105 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Serialize, Serializer};
+use crate::compiler::parser::{RootAst, Statement};
+use crate::compiler::graph::literal_type;
+use crate::compiler::parser::expression::First;
+use crate::compiler::resolved_type_tag::TypeTag;
+
+/// What the `compile` subcommand writes out.
+/// this is a placeholder until the program can be compiled into nodes; it only lists the declarations.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct CompiledProgram {
+    pub declarations: Vec<CompiledDeclaration>,
+}
+
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct CompiledDeclaration {
+    pub name: String,
+    /// spelled as in the source, such as `i64`. `null` if it is to be inferred.
+    #[serde(serialize_with = "serialize_type_tag")]
+    pub type_tag: TypeTag,
+    /// the right-hand side as written in the source
+    pub value: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<CompiledAttribute>,
+}
+
+/// `#[key = value]` on the declaration, passed through without being interpreted.
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct CompiledAttribute {
+    pub key: String,
+    /// the literal as written in the source, such as `10u8`, `true` or `"red"` with the quotes
+    pub value: String,
+}
+
+impl CompiledProgram {
+    /// fails if a declaration is annotated with an unknown type, or if there is a statement which cannot be compiled yet.
+    /// an unsuffixed integral literal without the annotation is typed as `default_int`.
+    pub fn compile(root: &RootAst, default_int: &TypeTag) -> Result<Self> {
+        let mut declarations = vec![];
+        for statement in root.commands() {
+            match statement {
+                Statement::Expression(_) => bail!("expression statements cannot be compiled yet"),
+                Statement::If { .. } => bail!("if statements cannot be compiled yet"),
+                Statement::While { .. } => bail!("while loops cannot be compiled yet"),
+                Statement::For { .. } => bail!("for loops cannot be compiled yet"),
+                Statement::Assignment { .. } => bail!("assignments cannot be compiled yet"),
+                _ => {}
+            }
+
+            if let Statement::NodeDeclaration { attributes, identifier, type_tag, rhs, .. } = statement {
+                let declared = match type_tag {
+                    Some(type_name) => Some(TypeTag::resolve(type_name)
+                        .ok_or_else(|| anyhow!("the type of `{name}` is unknown", name = identifier.as_str()))?),
+                    None => None,
+                };
+
+                let (type_tag, value) = match rhs.as_first() {
+                    Some(First::Variable { identifier }) => (declared.unwrap_or(TypeTag::ToBeInferred), identifier.as_str().to_string()),
+                    Some(First::MemberPath { path }) => (declared.unwrap_or(TypeTag::ToBeInferred), path.to_string()),
+                    Some(literal @ (First::IntegralLiteral { .. } | First::FloatLiteral { .. } | First::StringLiteral { .. } | First::True { .. } | First::False { .. })) => {
+                        let type_tag = literal_type(literal, declared.as_ref(), default_int)?;
+                        (declared.unwrap_or(type_tag), literal_spelling(literal))
+                    }
+                    _ => bail!("`{name}` must refer to a node, other expressions cannot be compiled yet", name = identifier.as_str()),
+                };
+
+                let attributes = attributes.iter()
+                    .map(|attribute| CompiledAttribute {
+                        key: attribute.key.as_str().to_string(),
+                        value: literal_spelling(&attribute.value),
+                    })
+                    .collect();
+
+                declarations.push(CompiledDeclaration {
+                    name: identifier.as_str().to_string(),
+                    type_tag,
+                    value,
+                    attributes,
+                });
+            }
+        }
+
+        Ok(Self {
+            declarations
+        })
+    }
+}
+
+fn literal_spelling(literal: &First) -> String {
+    match literal {
+        First::IntegralLiteral { sequence, suffix, .. } => format!("{sequence}{suffix}", suffix = suffix.as_deref().unwrap_or_default()),
+        First::FloatLiteral { sequence, .. } => sequence.clone(),
+        First::StringLiteral { sequence, .. } => format!("{sequence:?}"),
+        First::True { .. } => "true".to_string(),
+        First::False { .. } => "false".to_string(),
+        other => unreachable!("it has been checked to be a literal, but it was {other:?}"),
+    }
+}
+
+fn serialize_type_tag<S: Serializer>(type_tag: &TypeTag, serializer: S) -> Result<S::Ok, S::Error> {
+    match type_tag {
+        TypeTag::ToBeInferred => serializer.serialize_none(),
+        type_tag => serializer.collect_str(type_tag),
+    }
+}
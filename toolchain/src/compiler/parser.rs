@@ -1,43 +1,239 @@
-mod expression;
+pub mod error;
+pub mod expression;
+pub mod visitor;
 
-use anyhow::bail;
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
 use crate::compiler::lexer::{Lexer, Token};
+use crate::compiler::parser::error::ParseError;
+use crate::compiler::parser::expression::{Expression, First};
+use crate::compiler::span::Span;
 use crate::compiler::parser::Statement::NoMoreStatements;
 
-struct Parser {
-    lexer: Lexer
+/// the same as the interpreter, which is deep enough for any hand-written program.
+const DEFAULT_MAX_DEPTH: usize = 1024;
+
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    /// [RootAst] stops reading the source once it has found this many errors
+    max_errors: Option<usize>,
+    /// how deeply the expression or the block being read is nested, such as in parentheses or in a loop
+    depth: Cell<usize>,
+    max_depth: usize,
 }
 
-impl Parser {
-    fn with_lexer(lexer: Lexer) -> Self {
+impl<'a> Parser<'a> {
+    pub fn with_lexer(lexer: Lexer<'a>) -> Self {
+        Self {
+            lexer,
+            max_errors: None,
+            depth: Cell::new(0),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// an expression nested deeper than this, such as in parentheses or as an operand of a prefix operator, is rejected.
+    /// each block around it counts toward the limit as well, such as the body of a loop in an if statement.
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            ..self
+        }
+    }
+
+    pub fn with_max_errors(self, max_errors: usize) -> Self {
         Self {
-            lexer
+            max_errors: Some(max_errors),
+            ..self
         }
     }
 
-    fn parse<T: FromParser>(&self) -> Result<T, T::Err> {
+    pub fn parse<T: FromParser>(&self) -> Result<T, T::Err> {
         T::read(self)
     }
+
+    /// reads an expression of any precedence. see [Expression] for which node is the root.
+    /// it counts as a level of nesting, as the inside of parentheses does.
+    pub fn parse_expression(&self) -> Result<Expression, ParseError> {
+        self.nested(|parser| parser.parse::<Expression>())
+    }
+
+    /// runs `read` one level deeper. fails instead of overflowing the stack once the nesting exceeds the limit.
+    fn nested<T>(&self, read: impl FnOnce(&Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        if self.depth.get() >= self.max_depth {
+            let (_, span) = self.lexer.peek_spanned()?;
+            return Err(ParseError::Malformed {
+                message: format!("the expression is nested too deeply (the limit is {max})", max = self.max_depth),
+                span,
+            })
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        let read = read(self);
+        self.depth.set(self.depth.get() - 1);
+        read
+    }
+
+    /// the whole source, so that a caller can resolve spans after parsing.
+    pub fn source(&self) -> &str {
+        self.lexer.source()
+    }
+
+    /// whether the whole source has been consumed. a token which cannot be read does not count as the end.
+    pub fn is_at_end(&self) -> bool {
+        matches!(self.lexer.peek(), Ok(Token::EndOfFile))
+    }
+
+    /// consumes the next token if it is `expected`. otherwise, reports it as unexpected.
+    fn expect(&self, expected: Token, description: &str) -> Result<(), ParseError> {
+        match self.lexer.next_spanned()? {
+            (found, _) if found == expected => Ok(()),
+            (found, span) => Err(ParseError::unexpected(description, found, span)),
+        }
+    }
+
+    /// runs `read`. if it fails, rewinds the lexer as if nothing has been read.
+    fn attempt<T, E>(&self, read: impl FnOnce(&Self) -> Result<T, E>) -> Option<T> {
+        let mark = self.lexer.mark();
+        let read = read(self).ok();
+        if read.is_none() {
+            self.lexer.reset(mark);
+        }
+
+        read
+    }
+
+    /// consumes blank lines, so that the next token starts a statement or closes a block.
+    fn skip_newlines(&self) -> Result<(), ParseError> {
+        while self.lexer.peek()? == Token::NewLine {
+            self.lexer.next()?;
+        }
+
+        Ok(())
+    }
+
+    /// a line which ends with an operator is continued on the next line, as `1 +` and `2` in `var a = 1 +\n2`.
+    /// consumes the newlines after the operator, along with a comment at the end of the line.
+    fn skip_line_continuation(&self) -> Result<(), ParseError> {
+        while matches!(self.lexer.peek()?, Token::NewLine | Token::Comment { .. }) {
+            self.lexer.next()?;
+        }
+
+        Ok(())
+    }
+
+    /// consumes the next token, and reports it as unexpected.
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        let (found, span) = self.lexer.next_spanned()?;
+        Err(ParseError::unexpected(expected, found, span))
+    }
 }
 
-pub(in self) trait FromParser: Sized {
+pub trait FromParser: Sized {
     type Err;
     
     fn read(parser: &Parser) -> Result<Self, Self::Err>;
 }
 
-struct RootAst {
+#[derive(Debug)]
+pub struct RootAst {
     commands: Vec<Statement>,
 }
 
+impl RootAst {
+    /// for passes which rewrite the statements, such as constant folding.
+    pub fn from_commands(commands: Vec<Statement>) -> Self {
+        Self {
+            commands
+        }
+    }
+
+    pub fn commands(&self) -> &[Statement] {
+        &self.commands
+    }
+
+    pub fn into_commands(self) -> Vec<Statement> {
+        self.commands
+    }
+}
+
+impl RootAst {
+    /// reads a statement and the newline after it. returns `None` at the end of the file.
+    fn read_terminated_statement(parser: &Parser) -> Result<Option<Statement>, ParseError> {
+        let statement = parser.parse()?;
+        if let NoMoreStatements = statement {
+            return Ok(None)
+        }
+
+        // a statement is terminated by a newline or the end of the file.
+        // a trailing comment is left to be read as the next statement, which the newline terminates instead.
+        if parser.is_at_end() {
+            return Ok(Some(statement))
+        }
+
+        match parser.lexer.peek()? {
+            Token::NewLine => {
+                parser.lexer.next()?;
+            }
+            Token::Comment { .. } => {}
+            _ => {
+                let (found, span) = parser.lexer.next_spanned()?;
+                return Err(ParseError::Malformed {
+                    message: format!("trailing token {found:?} after the statement"),
+                    span,
+                })
+            }
+        }
+
+        Ok(Some(statement))
+    }
+
+    /// skips the rest of the broken statement, up to and including the next newline.
+    fn recover(parser: &Parser, error: &ParseError) {
+        // the statement boundary has already been consumed as the unexpected token
+        if let ParseError::UnexpectedToken { found: Token::NewLine | Token::EndOfFile, .. } = error {
+            return
+        }
+
+        loop {
+            let mark = parser.lexer.mark();
+            match parser.lexer.next() {
+                Ok(Token::NewLine | Token::EndOfFile) => break,
+                Ok(_) => {}
+                // the malformed token may not have been consumed at all
+                Err(_) => {
+                    parser.lexer.reset(mark);
+                    parser.lexer.skip_char();
+                }
+            }
+        }
+    }
+}
+
 impl FromParser for RootAst {
-    type Err = ();
+    /// every error found in the source, in the order of appearance.
+    type Err = Vec<ParseError>;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
         let mut vec = vec![];
+        let mut errors = vec![];
 
-        while let Ok(parsed_statement) = parser.parse() {
-            vec.push(parsed_statement);
+        loop {
+            match Self::read_terminated_statement(parser) {
+                Ok(Some(statement)) => vec.push(statement),
+                Ok(None) => break,
+                Err(error) => {
+                    Self::recover(parser, &error);
+                    errors.push(error);
+                    if parser.max_errors.is_some_and(|max| errors.len() >= max) {
+                        break
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
         }
 
         Ok(Self {
@@ -46,124 +242,532 @@ impl FromParser for RootAst {
     }
 }
 
-struct Identifier(String);
+#[derive(Debug)]
+pub struct Identifier {
+    name: String,
+    span: Span,
+}
+
+impl Identifier {
+    pub fn new(name: String, span: Span) -> Self {
+        Self {
+            name,
+            span,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
 
 impl FromParser for Identifier {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        match parser.lexer.peek() {
+        match parser.lexer.peek()? {
             Token::Identifier { inner } => {
-                parser.lexer.next();
-                Ok(Identifier(inner))
+                let (_, span) = parser.lexer.next_spanned()?;
+                Ok(Identifier::new(inner, span))
             }
-            other => bail!("{other:?} is unexpected, identifier was expected"),
+            _ => parser.unexpected("an identifier"),
         }
     }
 }
-enum Statement {
+
+#[derive(Debug)]
+pub enum Statement {
+    /// `var <identifier> = <expr>`, or `const <identifier> = <expr>` which cannot be assigned to afterwards.
     NodeDeclaration {
+        /// `#[key = value]` written before the declaration. they describe the node, such as its color,
+        /// and are kept as written.
+        attributes: Vec<Attribute>,
+        is_const: bool,
         identifier: Identifier,
         type_tag: Option<UnresolvedTypeName>,
-        rhs: IdentifierOrMemberPath,
+        rhs: Expression,
     },
     Comment {
         content: String,
     },
+    /// a bare expression such as `1 + 2`, which is evaluated for its value.
+    Expression(Expression),
+    /// ```text
+    /// if <expr> then
+    ///   <statements>
+    /// elseif <expr> then
+    ///   <statements>
+    /// else
+    ///   <statements>
+    /// end
+    /// ```
+    /// `elseif` can also be written as `else if`. both `elseif` and `else` branches are optional.
+    If {
+        condition: Expression,
+        then_block: Vec<Statement>,
+        elseif_branches: Vec<ElseIfBranch>,
+        else_block: Option<Vec<Statement>>,
+    },
+    /// ```text
+    /// while <expr>
+    ///   <statements>
+    /// wend
+    /// ```
+    While {
+        condition: Expression,
+        body: Vec<Statement>,
+    },
+    /// `<identifier or member path> = <expr>`. unlike [Statement::NodeDeclaration], the variable must be declared beforehand.
+    Assignment {
+        target: IdentifierOrMemberPath,
+        rhs: Expression,
+    },
+    /// ```text
+    /// for <identifier> = <expr> to <expr>
+    ///   <statements>
+    /// end
+    /// ```
+    /// both bounds are inclusive, so `for i = 1 to 3` runs with `i` being `1`, `2` and `3`.
+    For {
+        variable: Identifier,
+        from: Expression,
+        to: Expression,
+        body: Vec<Statement>,
+    },
     NoMoreStatements,
 }
 
+/// `key = value` in `#[key = value]`.
+#[derive(Debug)]
+pub struct Attribute {
+    pub key: Identifier,
+    /// an integral, string, or boolean literal. this is always a literal variant of [First].
+    pub value: First,
+}
+
+impl FromParser for Attribute {
+    type Err = ParseError;
+
+    fn read(parser: &Parser) -> Result<Self, Self::Err> {
+        let key = parser.parse()?;
+        parser.expect(Token::SymEq, "`=` after the attribute key")?;
+        let value = match parser.lexer.peek()? {
+            Token::Digits { .. } | Token::StringLiteral { .. } | Token::KeywordTrue | Token::KeywordFalse => parser.parse()?,
+            _ => return parser.unexpected("a literal as the attribute value"),
+        };
+
+        Ok(Self {
+            key,
+            value,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ElseIfBranch {
+    pub condition: Expression,
+    pub block: Vec<Statement>,
+}
+
+impl Statement {
+    fn read_if(parser: &Parser) -> Result<Self, ParseError> {
+        // `if` or `elseif` was consumed by the caller
+        let condition = Self::read_condition(parser)?;
+        let then_block = Self::read_if_block(parser)?;
+        let mut elseif_branches = vec![];
+        let mut else_block = None;
+
+        loop {
+            match parser.lexer.peek()? {
+                Token::KeywordElseIf => {
+                    parser.lexer.next()?;
+                    let condition = Self::read_condition(parser)?;
+                    let block = Self::read_if_block(parser)?;
+                    elseif_branches.push(ElseIfBranch { condition, block });
+                }
+                Token::KeywordElse if parser.lexer.peek_nth(1)? == Token::KeywordIf => {
+                    parser.lexer.next()?;
+                    parser.lexer.next()?;
+                    let condition = Self::read_condition(parser)?;
+                    let block = Self::read_if_block(parser)?;
+                    elseif_branches.push(ElseIfBranch { condition, block });
+                }
+                Token::KeywordElse => {
+                    parser.lexer.next()?;
+                    else_block = Some(Self::read_if_block(parser)?);
+                    parser.expect(Token::KeywordEnd, "`end` to close the if statement")?;
+                    break
+                }
+                Token::KeywordEnd => {
+                    parser.lexer.next()?;
+                    break
+                }
+                _ => return parser.unexpected("`end` to close the if statement"),
+            }
+        }
+
+        Ok(Self::If {
+            condition,
+            then_block,
+            elseif_branches,
+            else_block,
+        })
+    }
+
+    /// reads `var` or `const` and the rest of the declaration.
+    fn read_declaration(parser: &Parser, attributes: Vec<Attribute>) -> Result<Self, ParseError> {
+        let is_const = parser.lexer.next()? == Token::KeywordConst;
+        let ident = match parser.lexer.next_spanned()? {
+            (Token::Identifier { inner }, span) => Identifier::new(inner, span),
+            (found, span) => {
+                let expected = if is_const { "an identifier after `const`" } else { "an identifier after `var`" };
+                return Err(ParseError::unexpected(expected, found, span))
+            }
+        };
+
+        let type_tag = if parser.lexer.peek()? == Token::SymColon {
+            parser.lexer.next()?;
+            let type_tag = parser.parse::<UnresolvedTypeName>()?;
+            Some(type_tag)
+        } else {
+            None
+        };
+
+        parser.expect(Token::SymEq, "`=` after the declared variable")?;
+        let node = parser.parse_expression()?;
+
+        Ok(Self::NodeDeclaration {
+            attributes,
+            is_const,
+            identifier: ident,
+            type_tag,
+            rhs: node,
+        })
+    }
+
+    /// reads `#[key = value, ...]` as long as it continues. each group may be followed by a newline.
+    fn read_attributes(parser: &Parser) -> Result<Vec<Attribute>, ParseError> {
+        let mut attributes = vec![];
+        while parser.lexer.peek()? == Token::SymSharp {
+            parser.lexer.next()?;
+            parser.expect(Token::SymOpenBracket, "`[` after `#`")?;
+            loop {
+                attributes.push(parser.parse()?);
+                match parser.lexer.next_spanned()? {
+                    (Token::SymComma, _) => {}
+                    (Token::SymCloseBracket, _) => break,
+                    (found, span) => return Err(ParseError::unexpected("`,` or `]` in the attribute", found, span)),
+                }
+            }
+            parser.skip_newlines()?;
+        }
+
+        Ok(attributes)
+    }
+
+    fn read_condition(parser: &Parser) -> Result<Expression, ParseError> {
+        let condition = parser.parse_expression()?;
+        parser.expect(Token::KeywordThen, "`then` after the condition")?;
+
+        Ok(condition)
+    }
+
+    fn read_while(parser: &Parser) -> Result<Self, ParseError> {
+        // `while` was consumed by the caller
+        let condition = parser.parse_expression()?;
+        let body = Self::read_block(parser, |token| *token == Token::KeywordWend, "`wend` to close the while loop")?;
+        parser.lexer.next()?;
+
+        Ok(Self::While {
+            condition,
+            body,
+        })
+    }
+
+    fn read_for(parser: &Parser) -> Result<Self, ParseError> {
+        // `for` was consumed by the caller
+        let variable = parser.parse()?;
+        parser.expect(Token::SymEq, "`=` after the loop variable")?;
+        let from = parser.parse_expression()?;
+        parser.expect(Token::KeywordTo, "`to` after the lower bound")?;
+        let to = parser.parse_expression()?;
+        let body = Self::read_block(parser, |token| *token == Token::KeywordEnd, "`end` to close the for loop")?;
+        parser.lexer.next()?;
+
+        Ok(Self::For {
+            variable,
+            from,
+            to,
+            body,
+        })
+    }
+
+    fn read_if_block(parser: &Parser) -> Result<Vec<Statement>, ParseError> {
+        Self::read_block(
+            parser,
+            |token| matches!(token, Token::KeywordElseIf | Token::KeywordElse | Token::KeywordEnd),
+            "`end` to close the if statement"
+        )
+    }
+
+    /// reads statements until `is_block_end` holds, leaving that token unconsumed.
+    /// `expectation` describes the closing token for the error at the end of the file, such as "`end` to close the for loop".
+    /// a block counts as a level of nesting, as the inside of parentheses does.
+    fn read_block(parser: &Parser, is_block_end: fn(&Token) -> bool, expectation: &str) -> Result<Vec<Statement>, ParseError> {
+        parser.nested(|parser| Self::read_statements(parser, is_block_end, expectation))
+    }
+
+    fn read_statements(parser: &Parser, is_block_end: fn(&Token) -> bool, expectation: &str) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = vec![];
+        loop {
+            parser.skip_newlines()?;
+            if is_block_end(&parser.lexer.peek()?) {
+                break
+            }
+
+            let statement = parser.parse()?;
+            if let NoMoreStatements = statement {
+                return parser.unexpected(expectation)
+            }
+            statements.push(statement);
+
+            match parser.lexer.peek()? {
+                Token::NewLine => {
+                    parser.lexer.next()?;
+                }
+                // see RootAst::read_terminated_statement
+                Token::Comment { .. } => {}
+                other if is_block_end(&other) => {}
+                _ => return parser.unexpected("a newline after the statement"),
+            }
+        }
+
+        Ok(statements)
+    }
+}
+
 impl FromParser for Statement {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        match parser.lexer.peek() {
-            Token::VarKeyword => {
-                parser.lexer.next();
-                let ident = match parser.lexer.next() {
-                    Token::Identifier { inner } => inner,
-                    _ => bail!("Identifier expected")
-                };
-
-                let type_tag = if parser.lexer.peek() == Token::SymColon {
-                    parser.lexer.next();
-                    let type_tag = parser.parse::<UnresolvedTypeName>()?;
-                    Some(type_tag)
-                } else {
-                    None
-                };
-
-                assert_eq!(parser.lexer.next(), Token::SymEq, "SymEq expected");
-                let node = parser.parse::<IdentifierOrMemberPath>()?;
-
-                Ok(Self::NodeDeclaration {
-                    identifier: Identifier(ident),
-                    type_tag,
-                    rhs: node,
+        parser.skip_newlines()?;
+        match parser.lexer.peek()? {
+            Token::VarKeyword | Token::KeywordConst => Self::read_declaration(parser, vec![]),
+            Token::SymSharp => {
+                let (_, span) = parser.lexer.peek_spanned()?;
+                let attributes = Self::read_attributes(parser)?;
+                parser.skip_newlines()?;
+                match parser.lexer.peek()? {
+                    Token::VarKeyword | Token::KeywordConst => Self::read_declaration(parser, attributes),
+                    _ => Err(ParseError::Malformed {
+                        message: "attributes can only precede a declaration".to_string(),
+                        span,
+                    }),
+                }
+            }
+            Token::Comment { content } => {
+                parser.lexer.next()?;
+                Ok(Self::Comment {
+                    content,
                 })
             }
+            Token::KeywordIf => {
+                parser.lexer.next()?;
+                Self::read_if(parser)
+            }
+            Token::KeywordWhile => {
+                parser.lexer.next()?;
+                Self::read_while(parser)
+            }
+            Token::KeywordFor => {
+                parser.lexer.next()?;
+                Self::read_for(parser)
+            }
+            Token::Identifier { .. } => {
+                // `a.b = 1` is an assignment, but `a.b + 1` is an expression
+                let target = parser.attempt(|parser| {
+                    let target = parser.parse::<IdentifierOrMemberPath>()?;
+                    parser.expect(Token::SymEq, "`=` after the assignment target")?;
+                    Ok::<_, ParseError>(target)
+                });
+
+                match target {
+                    Some(target) => Ok(Self::Assignment {
+                        target,
+                        rhs: parser.parse_expression()?,
+                    }),
+                    None => parser.parse_expression().map(Self::Expression),
+                }
+            }
             Token::EndOfFile => {
                 Ok(NoMoreStatements)
             }
-            other_token => {
-                bail!("Unexpected token: {other_token:?}");
+            _ => parser.parse_expression().map(Self::Expression),
+        }
+    }
+}
+
+/// a type name such as `i32`, `a.b`, or `SyncRef<IValue<bool>>`.
+#[derive(Debug)]
+pub struct UnresolvedTypeName {
+    name: IdentifierOrMemberPath,
+    /// `i32` in `SyncRef<i32>`. this is empty if the type does not have type arguments.
+    arguments: Vec<UnresolvedTypeName>,
+}
+
+impl UnresolvedTypeName {
+    /// returns the name if this is a plain identifier without type arguments, such as `i32`.
+    pub fn as_identifier(&self) -> Option<&Identifier> {
+        match &self.name {
+            IdentifierOrMemberPath::Identifier(identifier) if self.arguments.is_empty() => Some(identifier),
+            _ => None,
+        }
+    }
+
+    /// returns the name without type arguments, such as `SyncRef` in `SyncRef<i32>`.
+    pub fn name(&self) -> &IdentifierOrMemberPath {
+        &self.name
+    }
+
+    pub fn arguments(&self) -> &[UnresolvedTypeName] {
+        &self.arguments
+    }
+
+    /// `unclosed` holds each `>>` which has closed the type argument list of the caller, in addition to its own.
+    fn read_nested(parser: &Parser, unclosed: &mut Vec<Span>) -> Result<Self, ParseError> {
+        let name = parser.parse()?;
+        let mut arguments = vec![];
+        // a type argument always starts with a name, so `1 as i32 < 2` is still a comparison.
+        // `a as i32 < b` is read as type arguments, though; such comparisons need parentheses.
+        if parser.lexer.peek()? == Token::SymLess && matches!(parser.lexer.peek_nth(1)?, Token::Identifier { .. }) {
+            parser.lexer.next()?;
+            loop {
+                arguments.push(Self::read_nested(parser, unclosed)?);
+                if unclosed.pop().is_some() {
+                    break
+                }
+
+                match parser.lexer.next_spanned()? {
+                    (Token::SymComma, _) => {}
+                    (Token::SymMore, _) => break,
+                    // `>>` closes this list and the enclosing one
+                    (Token::PartMoreMore, span) => {
+                        unclosed.push(span);
+                        break
+                    }
+                    (found, span) => return Err(ParseError::unexpected("`,` or `>` in the type arguments", found, span)),
+                }
             }
         }
+
+        Ok(Self {
+            name,
+            arguments,
+        })
     }
 }
 
-struct UnresolvedTypeName(IdentifierOrMemberPath);
+impl Display for UnresolvedTypeName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments = self.arguments.iter().map(ToString::to_string).collect::<Vec<_>>();
+            write!(f, "<{}>", arguments.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
 
 impl FromParser for UnresolvedTypeName {
-    type Err = <IdentifierOrMemberPath as FromParser>::Err;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        parser.parse().map(|a| Self(a))
+        let mut unclosed = vec![];
+        let type_name = Self::read_nested(parser, &mut unclosed)?;
+        if let Some(span) = unclosed.pop() {
+            return Err(ParseError::Malformed {
+                message: format!("`>` is unbalanced in {type_name}"),
+                span,
+            })
+        }
+
+        Ok(type_name)
     }
 }
 
-enum IdentifierOrMemberPath {
+#[derive(Debug)]
+pub enum IdentifierOrMemberPath {
     Identifier(Identifier),
     MemberPath(MemberPath),
 }
 
 impl FromParser for IdentifierOrMemberPath {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        if let Ok(identifier) = parser.parse() {
-            Ok(Self::Identifier(identifier))
-        } else if let Ok(member_path) = parser.parse() {
-            Ok(Self::MemberPath(member_path))
+        // an identifier is a prefix of a member path, so reading a member path never needs to backtrack
+        let MemberPath { mut pack } = parser.parse()?;
+        if pack.len() == 1 {
+            Ok(Self::Identifier(pack.remove(0)))
         } else {
-            bail!("expected member_path or identifier")
+            Ok(Self::MemberPath(MemberPath { pack }))
         }
     }
 }
 
-struct MemberPath {
+impl Display for IdentifierOrMemberPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentifierOrMemberPath::Identifier(identifier) => write!(f, "{}", identifier.as_str()),
+            IdentifierOrMemberPath::MemberPath(member_path) => write!(f, "{member_path}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MemberPath {
     pack: Vec<Identifier>,
 }
 
+impl MemberPath {
+    /// runs from the first name to the last one.
+    pub fn span(&self) -> Option<Span> {
+        let first = self.pack.first()?.span();
+        let last = self.pack.last()?.span();
+
+        Some(first.to(last))
+    }
+}
+
+impl Display for MemberPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names = self.pack.iter().map(Identifier::as_str).collect::<Vec<_>>();
+        write!(f, "{}", names.join("."))
+    }
+}
+
 impl FromParser for MemberPath {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
         let mut buf = vec![];
         loop {
-            match parser.lexer.peek() {
+            match parser.lexer.peek()? {
                 Token::Identifier { inner } => {
-                    parser.lexer.next();
-                    buf.push(Identifier(inner))
-                }
-                other => {
-                    bail!("{other:?} was not expected, identifier was expected")
+                    let (_, span) = parser.lexer.next_spanned()?;
+                    buf.push(Identifier::new(inner, span))
                 }
+                _ => return parser.unexpected("an identifier"),
             }
 
-            match parser.lexer.peek() {
+            match parser.lexer.peek()? {
                 Token::SymDot => {
-                    parser.lexer.next();
+                    parser.lexer.next()?;
                 }
                 _ => break,
             }
@@ -174,3 +778,290 @@ impl FromParser for MemberPath {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::ast_dump::{self, AstFormat};
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::expression::First;
+    use crate::compiler::parser::{IdentifierOrMemberPath, Parser, RootAst, Statement, UnresolvedTypeName};
+    use crate::compiler::span::Span;
+    use crate::interpreter::{Interpreter, SupportedTypeBox};
+
+    #[test]
+    fn syntax_error_in_the_middle_is_not_a_truncated_program() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("var a = b\nvar = c\nvar d = a\n")).parse::<RootAst>().expect_err("the second line is malformed");
+
+        assert_eq!(errors[0].to_string(), "SymEq is unexpected, expected an identifier after `var`");
+        assert_eq!(errors[0].span().start, 14);
+    }
+
+    #[test]
+    fn every_statement_of_a_valid_program_is_read() {
+        let root = Parser::with_lexer(Lexer::from_borrowed("var a = b\nvar c = a\nvar d = c\n")).parse::<RootAst>().expect("the source is valid");
+
+        assert_eq!(root.commands().len(), 3);
+    }
+
+    #[test]
+    fn comments_are_kept_as_statements() {
+        let root = Parser::with_lexer(Lexer::from_borrowed("// hello\nvar a = b\n// bye\n")).parse::<RootAst>().expect("the source is valid");
+
+        assert!(matches!(
+            root.commands(),
+            [Statement::Comment { content: first }, Statement::NodeDeclaration { .. }, Statement::Comment { content: second }]
+                if first == " hello" && second == " bye"
+        ));
+    }
+
+    fn parse(source: &str) -> RootAst {
+        Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid")
+    }
+
+    #[test]
+    fn else_if_is_the_same_as_elseif() {
+        let elseif = parse("if a then\n1\nelseif b then\n2\nelse\n3\nend\n");
+        let else_if = parse("if a then\n1\nelse if b then\n2\nelse\n3\nend\n");
+
+        for root in [&elseif, &else_if] {
+            assert!(matches!(
+                root.commands(),
+                [Statement::If { then_block, elseif_branches, else_block: Some(else_block), .. }]
+                    if then_block.len() == 1 && elseif_branches.len() == 1 && else_block.len() == 1
+            ), "{root:?}");
+        }
+        assert_eq!(ast_dump::dump(&elseif, AstFormat::Sexp), ast_dump::dump(&else_if, AstFormat::Sexp));
+    }
+
+    fn sexp(source: &str) -> String {
+        ast_dump::dump(&parse(source), AstFormat::Sexp)
+    }
+
+    #[test]
+    fn call_takes_comma_separated_arguments() {
+        assert_eq!(sexp("f()\n"), "(expr (call f))\n");
+        assert_eq!(sexp("f(1, g(2) + 3)\n"), "(expr (call f 1 (Add (call g 2) 3)))\n");
+    }
+
+    #[test]
+    fn conditional_is_below_logical_or_and_associates_to_the_right() {
+        assert_eq!(sexp("a || b ? c : d\n"), "(expr (Conditional (LogicalOr a b) c d))\n");
+        assert_eq!(sexp("a ? b : c ? d : e\n"), "(expr (Conditional a b (Conditional c d e)))\n");
+    }
+
+    #[test]
+    fn assignment_is_told_apart_from_an_equality_by_backtracking() {
+        assert_eq!(sexp("a.b = 1\n"), "(set a b 1)\n");
+        assert_eq!(sexp("a == 1\n"), "(expr (Equal a 1))\n");
+    }
+
+    #[test]
+    fn single_name_is_an_identifier_and_dotted_names_are_a_member_path() {
+        let read = |source| Parser::with_lexer(Lexer::from_borrowed(source)).parse::<IdentifierOrMemberPath>().expect("the source is a name");
+
+        assert!(matches!(read("a = 1"), IdentifierOrMemberPath::Identifier(identifier) if identifier.as_str() == "a"));
+        assert!(matches!(read("a.b.c = 1"), IdentifierOrMemberPath::MemberPath(path) if path.to_string() == "a.b.c"));
+    }
+
+    #[test]
+    fn binary_operators_of_the_same_level_associate_to_the_left() {
+        assert_eq!(sexp("a - b - c\n"), "(expr (Subtract (Subtract a b) c))\n");
+        assert_eq!(sexp("a / b / c\n"), "(expr (Divide (Divide a b) c))\n");
+        assert_eq!(Interpreter::new().execute(&parse("10 - 3 - 2\n")).expect("the program is valid"), [SupportedTypeBox::I32(5)]);
+    }
+
+    #[test]
+    fn each_binary_level_binds_tighter_than_the_one_above() {
+        assert_eq!(sexp("a == b && c != d\n"), "(expr (LogicalAnd (Equal a b) (NotEqual c d)))\n");
+        assert_eq!(sexp("a | b == c\n"), "(expr (BitwiseOr a (Equal b c)))\n");
+        assert_eq!(sexp("a & b | c ^ d\n"), "(expr (BitwiseOr (BitwiseAnd a b) (BitwiseXor c d)))\n");
+        assert_eq!(sexp("a < b == c > d\n"), "(expr (Equal (Less a b) (More c d)))\n");
+        assert_eq!(sexp("a <=> b + c\n"), "(expr (Spaceship a (Add b c)))\n");
+        assert_eq!(sexp("1 << 2 < 3\n"), "(expr (Less (LeftShift 1 2) 3))\n");
+        assert_eq!(sexp("a << b + c\n"), "(expr (LeftShift a (Add b c)))\n");
+        assert_eq!(sexp("-a * b\n"), "(expr (Multiply (Negate a) b))\n");
+    }
+
+    #[test]
+    fn type_arguments_are_nested_even_if_they_close_with_more_more() {
+        let read = |source| Parser::with_lexer(Lexer::from_borrowed(source)).parse::<UnresolvedTypeName>();
+
+        let type_name = read("SyncRef<IValue<bool>>\n").expect("the type name is valid");
+        assert_eq!(type_name.to_string(), "SyncRef<IValue<bool>>");
+        assert_eq!(type_name.arguments()[0].arguments()[0].to_string(), "bool");
+        assert_eq!(read("a.b<i32, f32>\n").expect("the type name is valid").arguments().len(), 2);
+
+        let error = read("IValue<i32>>\n").expect_err("`>>` closes one list too many");
+        assert_eq!(error.to_string(), "`>` is unbalanced in IValue<i32>");
+        assert_eq!(error.span().start, 10);
+    }
+
+    #[test]
+    fn declaration_without_eq_is_a_parse_error() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("var a: i32 1\n")).parse::<RootAst>().expect_err("`=` is missing");
+
+        assert_eq!(errors[0].to_string(), "Digits { sequence: \"1\", suffix: None } is unexpected, expected `=` after the declared variable");
+        assert_eq!(errors[0].span(), Span { start: 11, end: 12 });
+    }
+
+    #[test]
+    fn every_broken_statement_is_reported_in_order() {
+        let source = "var = 1\nvar b = 2\nvar 3 = c\nvar d = $ + 1\n";
+        let errors = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect_err("three lines are broken");
+        let starts = errors.iter().map(|error| error.span().start).collect::<Vec<_>>();
+
+        assert_eq!(starts, [4, 22, 36]);
+        assert_eq!(errors[2].to_string(), "`$` is not a valid character");
+    }
+
+    #[test]
+    fn parse_expression_counts_as_a_level_of_nesting() {
+        let parser = |source| Parser::with_lexer(Lexer::from_borrowed(source)).with_max_depth(1);
+
+        assert!(parser("1 + 2 * 3").parse_expression().is_ok());
+        let error = parser("(1)").parse_expression().expect_err("the parentheses are the second level");
+        assert_eq!(error.message(), "the expression is nested too deeply (the limit is 1)");
+        // every statement reads its expressions through it
+        assert!(parser("if true then\nend\nvar a = 1\n").parse::<RootAst>().is_ok());
+        assert!(parser("if (true) then\nend\n").parse::<RootAst>().is_err());
+        assert!(parser("var a = (1)\n").parse::<RootAst>().is_err());
+    }
+
+    #[test]
+    fn comment_may_follow_a_statement_on_the_same_line() {
+        assert_eq!(sexp("var a = 1 // one\na = 2 // two\n"), "(var a 1)\n(comment \" one\")\n(set a 2)\n(comment \" two\")\n");
+    }
+
+    #[test]
+    fn blank_lines_are_skipped_before_statements_and_inside_blocks() {
+        let source = "\n\nvar a = 1\n\n\nif true then\n\n1\n\nelse\n\n2\n\nend\n\n";
+
+        assert_eq!(sexp(source), "(var a 1)\n(if true (block (expr 1)) (block (expr 2)))\n");
+    }
+
+    #[test]
+    fn trailing_comma_in_a_list_is_rejected() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("[1,]\n")).parse::<RootAst>().expect_err("the comma is trailing");
+
+        assert_eq!(errors[0].to_string(), "a trailing comma is not allowed in the list");
+        assert_eq!(errors[0].span().start, 3);
+    }
+
+    #[test]
+    fn attributes_are_attached_to_the_following_declaration() {
+        let root = parse("#[color = \"red\"]\n#[order = 1]\nvar a = 1\n");
+        let [Statement::NodeDeclaration { attributes, .. }] = root.commands() else {
+            panic!("the attributes should be part of the declaration")
+        };
+
+        let keys = attributes.iter().map(|attribute| attribute.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["color", "order"]);
+        assert!(matches!(&attributes[0].value, First::StringLiteral { sequence, .. } if sequence == "red"));
+
+        let errors = Parser::with_lexer(Lexer::from_borrowed("#[order = 1]\n1\n")).parse::<RootAst>().expect_err("1 is not a declaration");
+        assert_eq!(errors[0].to_string(), "attributes can only precede a declaration");
+        assert_eq!(errors[0].span().start, 0);
+    }
+
+    #[test]
+    fn suffixed_literal_out_of_its_range_is_a_parse_error() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("var a = 300u8\n")).parse::<RootAst>().expect_err("300 is not a u8");
+        assert_eq!(errors[0].to_string(), "`300` is out of range for u8");
+        assert_eq!(errors[0].span().start, 8);
+        assert_eq!(errors[0].span(), Span { start: 8, end: 13 });
+
+        assert_eq!(sexp("-128i8\n"), "(expr -128i8)\n");
+        assert!(Parser::with_lexer(Lexer::from_borrowed("-129i8\n")).parse::<RootAst>().is_err());
+    }
+
+    #[test]
+    fn trailing_token_after_a_statement_is_reported() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("var a = 1 )\n")).parse::<RootAst>().expect_err("`)` is trailing");
+        assert_eq!(errors[0].to_string(), "trailing token SymRightPar after the statement");
+        assert_eq!(errors[0].span().start, 10);
+        assert_eq!(errors[0].span(), Span { start: 10, end: 11 });
+
+        let parser = Parser::with_lexer(Lexer::from_borrowed("1\n"));
+        assert!(!parser.is_at_end());
+        parser.parse::<RootAst>().expect("the source is valid");
+        assert!(parser.is_at_end());
+    }
+
+    #[test]
+    fn minus_before_digits_is_a_negative_literal_unless_it_follows_an_operand() {
+        assert_eq!(sexp("-1\n"), "(expr -1)\n");
+        assert_eq!(sexp("2 * -3\n"), "(expr (Multiply 2 -3))\n");
+        assert_eq!(sexp("1 -1\n"), "(expr (Subtract 1 1))\n");
+        assert_eq!(sexp("-a\n"), "(expr (Negate a))\n");
+    }
+
+    #[test]
+    fn trailing_operator_continues_the_expression_onto_the_next_line() {
+        assert_eq!(sexp("var a = 1 +\n  2 *\n\n  3\na ||\nb\n"), "(var a (Add 1 (Multiply 2 3)))\n(expr (LogicalOr a b))\n");
+
+        // an operator at the start of a line does not continue the previous one
+        assert!(Parser::with_lexer(Lexer::from_borrowed("var b = 1\n* 2\n")).parse::<RootAst>().is_err());
+    }
+
+    #[test]
+    fn bare_expression_is_an_expression_statement() {
+        let root = parse("1 + 2\n");
+
+        assert!(matches!(root.commands(), [Statement::Expression(_)]), "{root:?}");
+        assert_eq!(Interpreter::new().execute(&root).expect("the program is valid"), [SupportedTypeBox::I32(3)]);
+    }
+
+    #[test]
+    fn nesting_deeper_than_the_limit_is_rejected() {
+        let parser = Parser::with_lexer(Lexer::from_borrowed("((((1))))\n")).with_max_depth(4);
+        let errors = parser.parse::<RootAst>().expect_err("the expression is nested too deeply");
+
+        assert_eq!(errors[0].message(), "the expression is nested too deeply (the limit is 4)");
+        assert_eq!(errors[0].span(), Span { start: 4, end: 5 });
+    }
+
+    #[test]
+    fn prefix_operators_count_as_nesting() {
+        let parser = Parser::with_lexer(Lexer::from_borrowed("!!!!true\n")).with_max_depth(4);
+
+        assert!(parser.parse::<RootAst>().is_err());
+    }
+
+    #[test]
+    fn binary_operators_do_not_count_as_nesting() {
+        let source = format!("{}\n", vec!["(1)"; 100].join(" + "));
+        let parser = Parser::with_lexer(Lexer::from_borrowed(&source)).with_max_depth(2);
+
+        assert!(parser.parse::<RootAst>().is_ok());
+    }
+
+    #[test]
+    fn blocks_count_as_nesting() {
+        let parser = |source| Parser::with_lexer(Lexer::from_borrowed(source)).with_max_depth(2);
+
+        assert!(parser("if true then\nwhile false\nwend\nend\n").parse::<RootAst>().is_ok());
+        let errors = parser("if true then\nwhile false\n1\nwend\nend\n").parse::<RootAst>().expect_err("`1` is in the second block");
+        assert_eq!(errors[0].message(), "the expression is nested too deeply (the limit is 2)");
+    }
+
+    #[test]
+    fn match_may_have_comments_after_the_scrutinee_and_the_arms() {
+        let root = parse("var a = match 2 // the scrutinee\n// between arms\ncase 1 => \"one\" // an arm\ncase _ => \"other\"\nend\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("the program is valid"), [SupportedTypeBox::String("other".to_string())]);
+    }
+
+    #[test]
+    fn match_pattern_may_be_a_negative_or_float_literal() {
+        let root = parse("match -1\ncase -1 => 1\ncase _ => 2\nend\nmatch 1.5\ncase -1.5 => 1\ncase 1.5 => 2\ncase _ => 3\nend\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("the program is valid"), [SupportedTypeBox::I32(1), SupportedTypeBox::I32(2)]);
+    }
+
+    #[test]
+    fn match_pattern_must_be_a_literal() {
+        let errors = Parser::with_lexer(Lexer::from_borrowed("match 1\ncase a => 1\nend\n")).parse::<RootAst>().expect_err("`a` is not a literal");
+
+        assert_eq!(errors[0].message(), "Identifier { inner: \"a\" } is unexpected, expected a literal or `_` as a pattern");
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::{Display, Formatter};
+use crate::compiler::lexer::{LexError, LexErrorKind, Token};
+use crate::compiler::span::Span;
+
+/// Why the source could not be parsed. every variant knows where the problem is.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ParseError {
+    /// `found` is not allowed there. `expected` describes what is, such as "`=` after the loop variable".
+    UnexpectedToken {
+        expected: String,
+        found: Token,
+        span: Span,
+    },
+    UnterminatedString {
+        span: Span,
+    },
+    /// the lexer could not make a token, such as an invalid escape sequence or `$`.
+    InvalidToken {
+        message: String,
+        span: Span,
+    },
+    /// each token is fine, but they do not form a valid construct, such as `f(1,)`.
+    Malformed {
+        message: String,
+        span: Span,
+    },
+}
+
+impl ParseError {
+    pub fn unexpected(expected: &str, found: Token, span: Span) -> Self {
+        Self::UnexpectedToken {
+            expected: expected.to_string(),
+            found,
+            span,
+        }
+    }
+
+    /// what is wrong, without where it is.
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnexpectedToken { expected, found, .. } => format!("{found:?} is unexpected, expected {expected}"),
+            ParseError::UnterminatedString { .. } => "the string literal is not terminated".to_string(),
+            ParseError::InvalidToken { message, .. } => message.clone(),
+            ParseError::Malformed { message, .. } => message.clone(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. } => *span,
+            ParseError::UnterminatedString { span } => *span,
+            ParseError::InvalidToken { span, .. } => *span,
+            ParseError::Malformed { span, .. } => *span,
+        }
+    }
+}
+
+/// only what is wrong. [Diagnostic](crate::compiler::diagnostic::Diagnostic) tells where it is, in lines and columns.
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{message}", message = self.message())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        let span = Span {
+            start: error.index,
+            end: error.index + 1,
+        };
+
+        match error.kind {
+            LexErrorKind::UnterminatedString => ParseError::UnterminatedString { span },
+            unexpected @ LexErrorKind::UnexpectedChar(_) => ParseError::InvalidToken { message: unexpected.to_string(), span },
+            LexErrorKind::Malformed(message) => ParseError::InvalidToken { message, span },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::{LexError, LexErrorKind, Token};
+    use crate::compiler::parser::error::ParseError;
+    use crate::compiler::span::Span;
+
+    #[test]
+    fn lex_error_becomes_a_parse_error_at_the_same_char() {
+        let error = ParseError::from(LexError { index: 3, kind: LexErrorKind::UnexpectedChar('$') });
+
+        assert_eq!(error, ParseError::InvalidToken { message: "`$` is not a valid character".to_string(), span: Span { start: 3, end: 4 } });
+        assert_eq!(error.to_string(), "`$` is not a valid character");
+    }
+
+    #[test]
+    fn unexpected_token_names_what_was_expected() {
+        let error = ParseError::unexpected("`then` after the condition", Token::NewLine, Span { start: 5, end: 6 });
+
+        assert_eq!(error.message(), "NewLine is unexpected, expected `then` after the condition");
+        assert_eq!(error.span(), Span { start: 5, end: 6 });
+    }
+}
@@ -1,94 +1,514 @@
-use anyhow::bail;
+use strum::IntoStaticStr;
 use crate::compiler::lexer::Token;
-use crate::compiler::parser::{FromParser, Identifier, Parser, UnresolvedTypeName};
+use crate::compiler::parser::error::ParseError;
+use crate::compiler::parser::{FromParser, Identifier, MemberPath, Parser, UnresolvedTypeName};
+use crate::compiler::parser::visitor::{Visitable, Visitor};
+use crate::compiler::resolved_type_tag::TypeTag;
+use crate::compiler::span::Span;
 
-trait BinaryOperatorNode {
+pub trait BinaryOperatorNode {
     type OperatorEnum: Copy + FromParser;
     type Rhs;
 
     fn binary(operator: Self::OperatorEnum, lhs: Self, rhs: Self::Rhs) -> Self;
 }
 
-trait PropagateFrom<From> {
+pub trait PropagateFrom<From> {
     fn propagate(from: From) -> Self;
 }
 
+/// a cheap node which is left behind when a child is moved out of a node implementing [Drop].
+trait Placeholder {
+    fn placeholder() -> Self;
+}
+
+impl Placeholder for First {
+    fn placeholder() -> Self {
+        First::True { span: Span::default() }
+    }
+}
+
+impl Placeholder for Cast {
+    fn placeholder() -> Self {
+        Cast::Propagated(First::placeholder())
+    }
+}
+
+impl Placeholder for Unary {
+    fn placeholder() -> Self {
+        Unary::Propagated(Cast::placeholder())
+    }
+}
+
+/// the top of the precedence chain, which any expression can be parsed as.
+/// from the loosest to the tightest, the chain is [Conditional], [LogicalOrExpression], [LogicalAndExpression],
+/// [BitwiseOrExpression], [BitwiseXorExpression], [BitwiseAndExpression], [EqualityCheckExpression],
+/// [RelationCheckExpression], [BitwiseShift], [Additive], [Multiplicative], [Unary], [Cast] and [First].
+/// use [Parser::parse_expression] to read one.
+pub type Expression = Conditional;
+
+/// the range from the start of `first` to the end of `last`. either of them is enough if the other is unknown.
+fn cover(first: Option<Span>, last: Option<Span>) -> Option<Span> {
+    match (first, last) {
+        (Some(first), Some(last)) => Some(first.to(last)),
+        (first, last) => first.or(last),
+    }
+}
+
 // ------------------------------------------------
 
-enum First {
+#[derive(Debug)]
+pub enum First {
     IntegralLiteral {
         sequence: String,
+        /// `u8` in `10u8`
+        suffix: Option<String>,
+        /// includes the minus of a negative literal, and the suffix
+        span: Span,
+    },
+    /// `1.5`, `1e10`, `inf` or `nan`. the spec falls back to f32 when the context does not care
+    FloatLiteral {
+        sequence: String,
+        span: Span,
     },
     StringLiteral {
         sequence: String,
+        /// includes the quotes
+        span: Span,
     },
     Variable {
         identifier: Identifier,
     },
-    True,
-    False,
+    /// `a.b.c`, which refers to a member of a node.
+    MemberPath {
+        path: MemberPath,
+    },
+    /// `callee(arg1, arg2)`. a trailing comma, such as `callee(arg1,)`, is rejected.
+    Call {
+        callee: Identifier,
+        args: Vec<Expression>,
+    },
+    /// ```text
+    /// match <expr>
+    ///   case <pattern> => <expr>
+    /// end
+    /// ```
+    /// the first arm whose pattern matches the scrutinee is taken.
+    Match {
+        scrutinee: Box<Expression>,
+        arms: Vec<MatchArm>,
+    },
+    /// `[e1, e2, e3]`. `[]` is an empty list. a trailing comma is rejected as in [First::Call].
+    List {
+        elements: Vec<Expression>,
+    },
+    /// `target[index]`. it binds tighter than anything else, so `a[0][1]` is `(a[0])[1]`.
+    Index {
+        target: Box<First>,
+        index: Box<Expression>,
+    },
+    True {
+        span: Span,
+    },
+    False {
+        span: Span,
+    },
+    /// `( expr )`
+    Parenthesized(Box<Expression>),
+}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    pub value: Expression,
+}
+
+#[derive(Debug)]
+pub enum MatchPattern {
+    /// an integral, float, string, or boolean literal. a numeric one may have a leading `-`, such as `-1`.
+    /// this is always a literal variant of [First].
+    Literal(First),
+    /// `_`, which matches anything.
+    Wildcard,
+}
+
+impl FromParser for MatchPattern {
+    type Err = ParseError;
+
+    fn read(parser: &Parser) -> Result<Self, Self::Err> {
+        match parser.lexer.peek()? {
+            Token::Identifier { inner } if inner == "_" => {
+                parser.lexer.next()?;
+                Ok(Self::Wildcard)
+            }
+            Token::Digits { .. } | Token::FloatLiteral { .. } | Token::SymMinus | Token::StringLiteral { .. } | Token::KeywordTrue | Token::KeywordFalse => {
+                Ok(Self::Literal(parser.parse()?))
+            }
+            _ => parser.unexpected("a literal or `_` as a pattern"),
+        }
+    }
+}
+
+impl Visitable for MatchPattern {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            MatchPattern::Literal(literal) => literal.accept(visitor),
+            MatchPattern::Wildcard => visitor.visit_literal("_"),
+        }
+    }
 }
 
 impl FromParser for First {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        match parser.lexer.peek() {
-            Token::Identifier { inner } => {
-                parser.lexer.next();
-                let identifier = Identifier(inner);
+        let mut first = Self::read_primary(parser)?;
+        while parser.lexer.peek()? == Token::SymOpenBracket {
+            parser.lexer.next()?;
+            let index = parser.parse_expression()?;
+            parser.expect(Token::SymCloseBracket, "`]` to close the index")?;
+            first = Self::Index {
+                target: Box::new(first),
+                index: Box::new(index),
+            };
+        }
+
+        Ok(first)
+    }
+}
+
+impl First {
+    /// where the node is, as far as its literals and identifiers tell. parentheses and closing tokens are not included.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            First::IntegralLiteral { span, .. }
+            | First::FloatLiteral { span, .. }
+            | First::StringLiteral { span, .. }
+            | First::True { span }
+            | First::False { span } => Some(*span),
+            First::Variable { identifier } => Some(identifier.span()),
+            First::MemberPath { path } => path.span(),
+            First::Call { callee, args } => cover(Some(callee.span()), args.last().and_then(Expression::span)),
+            First::Match { scrutinee, arms } => cover(scrutinee.span(), arms.last().and_then(|arm| arm.value.span())),
+            First::List { elements } => cover(elements.first().and_then(Expression::span), elements.last().and_then(Expression::span)),
+            First::Index { target, index } => cover(target.span(), index.span()),
+            First::Parenthesized(inner) => inner.span(),
+        }
+    }
+
+    /// the type of an unsuffixed literal is decided later, so it only has to fit in the widest integral type.
+    fn integral_literal(sequence: String, suffix: Option<String>, span: Span) -> Result<Self, ParseError> {
+        let negative = sequence.starts_with('-');
+        let fits = match suffix.as_deref() {
+            Some(suffix) => TypeTag::from_name(suffix)
+                .filter(TypeTag::is_integral)
+                .unwrap_or_else(|| unreachable!("the lexer only accepts integral suffixes, but it was {suffix}"))
+                .can_hold(&sequence),
+            None if negative => TypeTag::I64.can_hold(&sequence),
+            None => TypeTag::U64.can_hold(&sequence),
+        };
+
+        if !fits {
+            let message = match &suffix {
+                Some(suffix) if negative && suffix.starts_with('u') => format!("`{sequence}{suffix}` is negative, but {suffix} is unsigned"),
+                Some(suffix) => format!("`{sequence}` is out of range for {suffix}"),
+                None => format!("`{sequence}` is out of range for any integral type"),
+            };
+
+            return Err(ParseError::Malformed { message, span })
+        }
+
+        Ok(Self::IntegralLiteral {
+            sequence,
+            suffix,
+            span,
+        })
+    }
+
+    /// the type is decided later, so it only has to fit in f64, the widest float type.
+    /// `1e400` is rejected instead of being rounded to infinity, which has to be written as `inf`.
+    fn float_literal(sequence: String, span: Span) -> Result<Self, ParseError> {
+        let infinite = sequence.parse::<f64>().map_or(true, f64::is_infinite);
+        if infinite && sequence.trim_start_matches('-') != "inf" {
+            return Err(ParseError::Malformed {
+                message: format!("`{sequence}` is out of range for f64"),
+                span,
+            })
+        }
+
+        Ok(Self::FloatLiteral {
+            sequence,
+            span,
+        })
+    }
+
+    /// the minus is a part of the literal, so that `-128i8` fits in i8 and `-1u8` is rejected as a literal.
+    /// `-1 as u8` is still `-(1 as u8)`, because a prefix operator binds looser than a cast.
+    /// `-1.5` and `-inf` are single literals as well, although they have the same value as the negation.
+    fn starts_negative_literal(parser: &Parser) -> Result<bool, ParseError> {
+        Ok(parser.lexer.peek()? == Token::SymMinus
+            && matches!(parser.lexer.peek_nth(1)?, Token::Digits { .. } | Token::FloatLiteral { .. })
+            && parser.lexer.peek_nth(2)? != Token::KeywordAs)
+    }
+
+    /// reads a [First] without the indices after it.
+    fn read_primary(parser: &Parser) -> Result<Self, ParseError> {
+        match parser.lexer.peek()? {
+            Token::Identifier { inner } if parser.lexer.peek_nth(1)? == Token::SymLeftPar => {
+                let (_, span) = parser.lexer.next_spanned()?;
+                parser.lexer.next()?;
+                Ok(First::Call {
+                    callee: Identifier::new(inner, span),
+                    args: Self::read_comma_separated(parser, Token::SymRightPar, "`)`", "argument list")?,
+                })
+            }
+            Token::Identifier { .. } => {
+                let MemberPath { mut pack } = parser.parse()?;
+                if pack.len() > 1 {
+                    return Ok(First::MemberPath { path: MemberPath { pack } })
+                }
+
                 let var_node = First::Variable {
-                    identifier
+                    identifier: pack.remove(0),
                 };
 
                 Ok(var_node)
             }
-            Token::Digits { sequence } => {
-                Ok(Self::IntegralLiteral {
-                    sequence
-                })
+            Token::SymMinus if Self::starts_negative_literal(parser)? => {
+                let (_, minus) = parser.lexer.next_spanned()?;
+                match parser.lexer.next_spanned()? {
+                    (Token::Digits { sequence, suffix }, digits) => {
+                        Self::integral_literal(format!("-{sequence}"), suffix, Span { start: minus.start, end: digits.end })
+                    }
+                    (Token::FloatLiteral { sequence }, digits) => {
+                        Self::float_literal(format!("-{sequence}"), Span { start: minus.start, end: digits.end })
+                    }
+                    _ => unreachable!("it has been peeked"),
+                }
+            }
+            Token::Digits { sequence, suffix } => {
+                let (_, span) = parser.lexer.next_spanned()?;
+                Self::integral_literal(sequence, suffix, span)
+            }
+            Token::FloatLiteral { sequence } => {
+                let (_, span) = parser.lexer.next_spanned()?;
+                Self::float_literal(sequence, span)
             }
             Token::StringLiteral { content } => {
-                Ok(Self::StringLiteral { sequence: content })
+                let (_, span) = parser.lexer.next_spanned()?;
+                Ok(Self::StringLiteral { sequence: content, span })
+            }
+            Token::KeywordMatch => {
+                parser.lexer.next()?;
+                Self::read_match(parser)
             }
             Token::KeywordTrue => {
-                Ok(Self::True)
+                let (_, span) = parser.lexer.next_spanned()?;
+                Ok(Self::True { span })
             }
             Token::KeywordFalse => {
-                Ok(Self::False)
+                let (_, span) = parser.lexer.next_spanned()?;
+                Ok(Self::False { span })
+            }
+            Token::SymOpenBracket => {
+                parser.lexer.next()?;
+                Ok(Self::List {
+                    elements: Self::read_comma_separated(parser, Token::SymCloseBracket, "`]`", "list")?,
+                })
+            }
+            Token::SymLeftPar => {
+                parser.lexer.next()?;
+                let inner = parser.parse_expression()?;
+                parser.expect(Token::SymRightPar, "`)` to close the parenthesized expression")?;
+                Ok(Self::Parenthesized(Box::new(inner)))
+            }
+            _ => parser.unexpected("an expression"),
+        }
+    }
+
+    /// the `match` keyword must be consumed by the caller.
+    fn read_match(parser: &Parser) -> Result<Self, ParseError> {
+        let scrutinee = Box::new(parser.parse_expression()?);
+        // a comment may follow the scrutinee or an arm on the same line
+        if matches!(parser.lexer.peek()?, Token::Comment { .. }) {
+            parser.lexer.next()?;
+        }
+        parser.expect(Token::NewLine, "a newline after the scrutinee")?;
+
+        let mut arms = vec![];
+        loop {
+            match parser.lexer.next_significant()? {
+                (Token::KeywordEnd, span) => {
+                    if arms.is_empty() {
+                        return Err(ParseError::Malformed {
+                            message: "a match expression must have at least one arm".to_string(),
+                            span,
+                        })
+                    }
+
+                    break
+                }
+                (Token::KeywordCase, _) => {
+                    let pattern = parser.parse()?;
+                    parser.expect(Token::PartEqMore, "`=>` after the pattern")?;
+                    let value = parser.parse_expression()?;
+                    arms.push(MatchArm { pattern, value });
+
+                    if !matches!(parser.lexer.peek()?, Token::NewLine | Token::Comment { .. } | Token::KeywordEnd) {
+                        return parser.unexpected("a newline or `end` after the arm")
+                    }
+                }
+                (found, span) => return Err(ParseError::unexpected("`case` or `end` in the match expression", found, span)),
+            }
+        }
+
+        Ok(Self::Match { scrutinee, arms })
+    }
+
+    /// reads expressions separated by `,` until `closing`, which is spelled as `closing_spelling` in errors.
+    /// the opening token must be consumed by the caller. `what` names the construct, such as "argument list".
+    fn read_comma_separated(parser: &Parser, closing: Token, closing_spelling: &str, what: &str) -> Result<Vec<Expression>, ParseError> {
+        let mut items = vec![];
+        if parser.lexer.peek()? == closing {
+            parser.lexer.next()?;
+            return Ok(items)
+        }
+
+        loop {
+            let (next, span) = parser.lexer.peek_spanned()?;
+            if next == closing {
+                return Err(ParseError::Malformed {
+                    message: format!("a trailing comma is not allowed in the {what}"),
+                    span,
+                })
             }
-            other => {
-                bail!("unexpected token: {other:?}")
+            items.push(parser.parse_expression()?);
+            match parser.lexer.next_spanned()? {
+                (Token::SymComma, _) => {}
+                (found, _) if found == closing => return Ok(items),
+                (found, span) => return Err(ParseError::unexpected(&format!("`,` or {closing_spelling} in the {what}"), found, span)),
             }
         }
     }
 }
+
+impl Visitable for First {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        if let First::Parenthesized(inner) = self {
+            // grouping does not add any depth by itself
+            inner.accept(visitor);
+            return
+        }
+
+        visitor.enter_expression();
+        match self {
+            First::IntegralLiteral { sequence, suffix, .. } => {
+                visitor.visit_literal(&format!("{sequence}{suffix}", suffix = suffix.as_deref().unwrap_or_default()));
+            }
+            First::FloatLiteral { sequence, .. } => visitor.visit_literal(sequence),
+            First::StringLiteral { sequence, .. } => visitor.visit_literal(&format!("{sequence:?}")),
+            First::Variable { identifier } => identifier.accept(visitor),
+            First::MemberPath { path } => path.accept(visitor),
+            First::Call { callee, args } => {
+                visitor.visit_operator("call");
+                callee.accept(visitor);
+                for arg in args {
+                    arg.accept(visitor);
+                }
+            }
+            First::Match { scrutinee, arms } => {
+                visitor.visit_operator("match");
+                scrutinee.accept(visitor);
+                for arm in arms {
+                    visitor.enter_expression();
+                    visitor.visit_operator("case");
+                    arm.pattern.accept(visitor);
+                    arm.value.accept(visitor);
+                    visitor.leave_expression();
+                }
+            }
+            First::List { elements } => {
+                visitor.visit_operator("list");
+                for element in elements {
+                    element.accept(visitor);
+                }
+            }
+            First::Index { target, index } => {
+                visitor.visit_operator("index");
+                target.accept(visitor);
+                index.accept(visitor);
+            }
+            First::True { .. } => visitor.visit_literal("true"),
+            First::False { .. } => visitor.visit_literal("false"),
+            First::Parenthesized(_) => unreachable!("handled above"),
+        }
+        visitor.leave_expression();
+    }
+}
+
 // ------------------------------------------------
 
 /// left-associative
 /// e.g. `1 as u16 as u32` is equivalent with `(1 as u16) as u32`.
-enum Cast {
+#[derive(Debug)]
+pub enum Cast {
     Do {
-        lhs: Box<Self>,
+        /// the value being casted. a cast has no right-hand side except for the type.
+        operand: Box<Self>,
         tp: UnresolvedTypeName,
     },
     Propagated(First),
 }
 
+impl Cast {
+    /// returns the operand if this is just a [First], such as a literal or a variable.
+    pub fn as_first(&self) -> Option<&First> {
+        match self {
+            Cast::Do { .. } => None,
+            Cast::Propagated(first) => Some(first),
+        }
+    }
+
+    /// see [First::span].
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Cast::Do { operand, tp } => cover(operand.span(), tp.as_identifier().map(Identifier::span)),
+            Cast::Propagated(first) => first.span(),
+        }
+    }
+}
+
 impl FromParser for Cast {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn read(parser: &Parser) -> Result<Self, Self::Err> {
-        let first_term = parser.parse::<Cast>()?;
-        if parser.lexer.peek() == Token::KeywordAs {
-            parser.lexer.next();
+        let first_term = parser.parse::<First>()?;
+        let mut acc = Self::Propagated(first_term);
+
+        while parser.lexer.peek()? == Token::KeywordAs {
+            parser.lexer.next()?;
             let type_name = parser.parse()?;
-            Ok(Self::Do {
-                lhs: Box::new(first_term),
+            acc = Self::Do {
+                operand: Box::new(acc),
                 tp: type_name
-            })
-        } else {
-            Ok(first_term)
+            };
+        }
+
+        Ok(acc)
+    }
+}
+
+impl Visitable for Cast {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            Cast::Do { operand, tp } => {
+                visitor.enter_expression();
+                visitor.visit_operator("As");
+                operand.accept(visitor);
+                tp.accept(visitor);
+                visitor.leave_expression();
+            }
+            Cast::Propagated(first) => first.accept(visitor),
         }
     }
 }
@@ -98,9 +518,8 @@ impl FromParser for Cast {
 macro_rules! binary_expression_node {
     ($name:ident, assoc: left, derive: $propagate_from:ident, rhs: $rhs:ty, operator: $operators:ty) => {
         #[doc="left-associative"]
-        enum $name {
-            // We'll handle them in the future
-            #[allow(dead_code)]
+        #[derive(Debug)]
+        pub enum $name {
             Binary {
                 operator: <Self as BinaryOperatorNode>::OperatorEnum,
                 lhs: Box<Self>,
@@ -112,9 +531,8 @@ macro_rules! binary_expression_node {
     };
     ($name:ident, assoc: right, derive: $propagate_from:ident, rhs: $rhs:ty, operator: $operators:ty) => {
         #[doc="right-associative"]
-        enum $name {
-            // We'll handle them in the future
-            #[allow(dead_code)]
+        #[derive(Debug)]
+        pub enum $name {
             Binary {
                 operator: <Self as BinaryOperatorNode>::OperatorEnum,
                 lhs: Box<Self>,
@@ -134,6 +552,28 @@ macro_rules! binary_expression_node_0 {
             }
         }
 
+        impl Placeholder for $name {
+            fn placeholder() -> Self {
+                Self::Propagated($propagate_from::placeholder())
+            }
+        }
+
+        impl Drop for $name {
+            /// drops the chain of left operands with a loop. the derived drop would recurse into each of them,
+            /// which overflows the stack on a long chain such as `1 + 1 + ...`.
+            fn drop(&mut self) {
+                let Self::Binary { lhs, .. } = self else {
+                    return
+                };
+
+                let mut next = std::mem::replace(lhs, Box::new(Self::placeholder()));
+                while let Self::Binary { lhs, .. } = next.as_mut() {
+                    // the replaced node only holds the placeholder now, so dropping it does not go any deeper
+                    next = std::mem::replace(lhs, Box::new(Self::placeholder()));
+                }
+            }
+        }
+
         impl BinaryOperatorNode for $name {
             type OperatorEnum = $operators;
             type Rhs = $rhs;
@@ -145,52 +585,114 @@ macro_rules! binary_expression_node_0 {
                 }
             }
         }
+
+        impl $name {
+            /// returns the operand if this is just a [First], such as a literal or a variable.
+            pub fn as_first(&self) -> Option<&First> {
+                match self {
+                    Self::Binary { .. } => None,
+                    Self::Propagated(inner) => inner.as_first(),
+                }
+            }
+
+            /// splits a chain such as `a + b - c` into `a` and `[(+, b), (-, c)]` in the order of evaluation.
+            /// this walks the chain with a loop, so that a long chain does not overflow the stack.
+            pub fn operands(&self) -> (&$propagate_from, Vec<(<Self as BinaryOperatorNode>::OperatorEnum, &$rhs)>) {
+                let mut rest = vec![];
+                let mut node = self;
+                loop {
+                    match node {
+                        Self::Binary { operator, lhs, rhs } => {
+                            rest.push((*operator, rhs.as_ref()));
+                            node = lhs.as_ref();
+                        }
+                        Self::Propagated(first) => {
+                            rest.reverse();
+                            return (first, rest)
+                        }
+                    }
+                }
+            }
+
+            /// same as [Self::operands], but takes the operands out of the chain.
+            pub fn into_operands(mut self) -> ($propagate_from, Vec<(<Self as BinaryOperatorNode>::OperatorEnum, $rhs)>) {
+                let mut rest = vec![];
+                loop {
+                    match &mut self {
+                        Self::Binary { operator, lhs, rhs } => {
+                            rest.push((*operator, std::mem::replace(rhs.as_mut(), <$rhs>::placeholder())));
+                            let lhs = std::mem::replace(lhs.as_mut(), Self::placeholder());
+                            self = lhs;
+                        }
+                        Self::Propagated(first) => {
+                            rest.reverse();
+                            return (std::mem::replace(first, $propagate_from::placeholder()), rest)
+                        }
+                    }
+                }
+            }
+
+            /// see [First::span].
+            pub fn span(&self) -> Option<Span> {
+                match self {
+                    Self::Binary { lhs, rhs, .. } => cover(lhs.span(), rhs.span()),
+                    Self::Propagated(inner) => inner.span(),
+                }
+            }
+        }
+
+        impl Visitable for $name {
+            fn accept<V: Visitor>(&self, visitor: &mut V) {
+                match self {
+                    Self::Binary { operator, lhs, rhs } => {
+                        visitor.enter_expression();
+                        visitor.visit_operator(operator.into());
+                        lhs.accept(visitor);
+                        rhs.accept(visitor);
+                        visitor.leave_expression();
+                    }
+                    Self::Propagated(inner) => inner.accept(visitor),
+                }
+            }
+        }
     };
 }
 
 macro_rules! parse_binary_expression_node {
     (left_assoc, $name:ty) => {
         impl FromParser for $name {
-            type Err = anyhow::Error;
+            type Err = ParseError;
 
             fn read(parser: &Parser) -> Result<Self, Self::Err> {
                 let first_term = parser.parse()?;
-                let operator = <<Self as BinaryOperatorNode>::OperatorEnum as FromParser>::read(parser);
+                let mut acc = Self::Propagated(first_term);
 
-                if let Ok(operator_token) = operator {
-                    parser.lexer.next();
-                    let lhs = Self::Propagated(first_term);
+                // the operator is consumed by its FromParser impl only if it matches
+                while let Ok(operator) = <<Self as BinaryOperatorNode>::OperatorEnum as FromParser>::read(parser) {
+                    parser.skip_line_continuation()?;
                     let rhs = parser.parse()?;
-
-                    let mut acc = Self::binary(operator_token, lhs, rhs);
-                    let next = <<Self as BinaryOperatorNode>::OperatorEnum as FromParser>::read(parser);
-                    while let Ok(op) = next {
-                        let new_rhs = parser.parse()?;
-                        // 左結合になるように詰め替える
-                        acc = Self::binary(op, acc, new_rhs);
-                    }
-                    Ok(acc)
-                } else {
-                    Ok(Self::Propagated(first_term))
+                    // 左結合になるように詰め替える
+                    acc = Self::binary(operator, acc, rhs);
                 }
+
+                Ok(acc)
             }
         }
     };
     (right_assoc, $name:ty) => {
-                impl FromParser for $name {
-            type Err = anyhow::Error;
+        impl FromParser for $name {
+            type Err = ParseError;
 
             fn read(parser: &Parser) -> Result<Self, Self::Err> {
                 let first_term = parser.parse()?;
                 let operator = <Self as BinaryOperatorNode>::OperatorEnum::read(parser);
 
                 if let Ok(operator_token) = operator {
-                    parser.lexer.next();
                     let lhs = Self::Propagated(first_term);
+                    parser.skip_line_continuation()?;
                     let rhs = parser.parse()?;
 
-                    let mut acc = Self::binary(operator_token, lhs, Self::Propagated(rhs));
-                    Ok(acc)
+                    Ok(Self::binary(operator_token, lhs, Self::Propagated(rhs)))
                 } else {
                     Ok(Self::Propagated(first_term))
                 }
@@ -203,15 +705,15 @@ macro_rules! parse_binary_expression_node {
 macro_rules! operator_from_parser {
     ($name:ty, $($token:ident => $variant:ident),+) => {
         impl FromParser for $name {
-            type Err = anyhow::Error;
+            type Err = ParseError;
 
             fn read(parser: &Parser) -> Result<Self, Self::Err> {
-                let op = match parser.lexer.peek() {
-                    $(Token::$token => Self::$variant,)+
-                    other => excess_token!(other)
+                let op = match parser.lexer.peek_spanned()? {
+                    $((Token::$token, _) => Self::$variant,)+
+                    (found, span) => excess_token!($name, found, span)
                 };
 
-                parser.lexer.next();
+                parser.lexer.next()?;
                 Ok(op)
             }
         }
@@ -219,16 +721,93 @@ macro_rules! operator_from_parser {
 }
 
 macro_rules! excess_token {
-    ($expr:expr) => {
-        bail!("excess token: {token:?}", token = $expr)
+    ($name:ty, $found:expr, $span:expr) => {
+        return Err(ParseError::unexpected(concat!("an operator of ", stringify!($name)), $found, $span))
     }
 }
 // ------------------------------------------------
 
-binary_expression_node!(Multiplicative, assoc: left, derive: Cast, rhs: Self, operator: MultiplicativeOps);
+/// prefix operators. they bind looser than casts, so `-1 as u32` is equivalent with `-(1 as u32)`.
+#[derive(Debug)]
+pub enum Unary {
+    Do {
+        operator: UnaryOps,
+        operand: Box<Self>,
+    },
+    Propagated(Cast),
+}
+
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum UnaryOps {
+    /// `-`
+    Negate,
+    /// `!`
+    LogicalNot,
+    /// `~`
+    BitwiseNot,
+}
+
+operator_from_parser!(UnaryOps, SymMinus => Negate, SymBang => LogicalNot, SymTilde => BitwiseNot);
+
+impl Unary {
+    /// returns the operand if this is just a [First], such as a literal or a variable.
+    pub fn as_first(&self) -> Option<&First> {
+        match self {
+            Unary::Do { .. } => None,
+            Unary::Propagated(cast) => cast.as_first(),
+        }
+    }
+
+    /// see [First::span]. the operator is not included.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Unary::Do { operand, .. } => operand.span(),
+            Unary::Propagated(cast) => cast.span(),
+        }
+    }
+}
 
-#[derive(Copy, Clone)]
-enum MultiplicativeOps {
+impl FromParser for Unary {
+    type Err = ParseError;
+
+    fn read(parser: &Parser) -> Result<Self, Self::Err> {
+        // the minus of a negative literal is read by First
+        if First::starts_negative_literal(parser)? {
+            return parser.parse().map(Self::Propagated)
+        }
+
+        if let Ok(operator) = parser.parse::<UnaryOps>() {
+            let operand = parser.nested(|parser| parser.parse::<Unary>())?;
+            Ok(Self::Do {
+                operator,
+                operand: Box::new(operand),
+            })
+        } else {
+            parser.parse().map(Self::Propagated)
+        }
+    }
+}
+
+impl Visitable for Unary {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            Unary::Do { operator, operand } => {
+                visitor.enter_expression();
+                visitor.visit_operator(operator.into());
+                operand.accept(visitor);
+                visitor.leave_expression();
+            }
+            Unary::Propagated(cast) => cast.accept(visitor),
+        }
+    }
+}
+
+// ------------------------------------------------
+
+binary_expression_node!(Multiplicative, assoc: left, derive: Unary, rhs: Unary, operator: MultiplicativeOps);
+
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum MultiplicativeOps {
     /// `*`
     Multiply,
     /// `/`
@@ -237,16 +816,16 @@ enum MultiplicativeOps {
     Reminder,
 }
 
-operator_from_parser!(MultiplicativeOps, SymAsterisk => Multiply, SymSlash => Divide, SymPlus => Reminder);
+operator_from_parser!(MultiplicativeOps, SymAsterisk => Multiply, SymSlash => Divide, SymPercent => Reminder);
 
 parse_binary_expression_node!(left_assoc, Multiplicative);
 
 // ------------------------------------------------
 
-binary_expression_node!(Additive, assoc: left, derive: Multiplicative, rhs: Self, operator: AdditiveOps);
+binary_expression_node!(Additive, assoc: left, derive: Multiplicative, rhs: Multiplicative, operator: AdditiveOps);
 
-#[derive(Copy, Clone)]
-enum AdditiveOps {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum AdditiveOps {
     Add,
     Subtract,
 }
@@ -258,21 +837,21 @@ parse_binary_expression_node!(left_assoc, Additive);
 
 binary_expression_node!(BitwiseShift, assoc: left, derive: Additive, rhs: Additive, operator: BitwiseShiftOps);
 
-#[derive(Copy, Clone)]
-enum BitwiseShiftOps {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum BitwiseShiftOps {
     LeftShift,
     RightShift,
 }
 
-operator_from_parser!(BitwiseShiftOps, PartMoreMore => LeftShift, PartLessLess => RightShift);
+operator_from_parser!(BitwiseShiftOps, PartLessLess => LeftShift, PartMoreMore => RightShift);
 
 parse_binary_expression_node!(left_assoc, BitwiseShift);
 // ------------------------------------------------
 
 binary_expression_node!(RelationCheckExpression, assoc: left, derive: BitwiseShift, rhs: BitwiseShift, operator: RelationCheckExpressionOps);
 
-#[derive(Copy, Clone)]
-enum RelationCheckExpressionOps {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum RelationCheckExpressionOps {
     Less,
     LessEqual,
     More,
@@ -288,8 +867,8 @@ parse_binary_expression_node!(left_assoc, RelationCheckExpression);
 
 binary_expression_node!(EqualityCheckExpression, assoc: left, derive: RelationCheckExpression, rhs: RelationCheckExpression, operator: EqualityCheckExpressionOps);
 
-#[derive(Copy, Clone)]
-enum EqualityCheckExpressionOps {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum EqualityCheckExpressionOps {
     Equal,
     NotEqual,
 }
@@ -302,8 +881,8 @@ parse_binary_expression_node!(left_assoc, EqualityCheckExpression);
 
 binary_expression_node!(BitwiseAndExpression, assoc: left, derive: EqualityCheckExpression, rhs: EqualityCheckExpression, operator: BitwiseAndExpressionOp);
 
-#[derive(Copy, Clone)]
-enum BitwiseAndExpressionOp {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum BitwiseAndExpressionOp {
     BitwiseAnd,
 }
 
@@ -314,8 +893,8 @@ parse_binary_expression_node!(left_assoc, BitwiseAndExpression);
 
 binary_expression_node!(BitwiseXorExpression, assoc: left, derive: BitwiseAndExpression, rhs: BitwiseAndExpression, operator: BitwiseXorExpressionOp);
 
-#[derive(Copy, Clone)]
-enum BitwiseXorExpressionOp {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum BitwiseXorExpressionOp {
     BitwiseXor
 }
 
@@ -327,8 +906,8 @@ parse_binary_expression_node!(left_assoc, BitwiseXorExpression);
 
 binary_expression_node!(BitwiseOrExpression, assoc: left, derive: BitwiseXorExpression, rhs: BitwiseXorExpression, operator: BitwiseOrExpressionOp);
 
-#[derive(Copy, Clone)]
-enum BitwiseOrExpressionOp {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum BitwiseOrExpressionOp {
     BitwiseOr,
 }
 
@@ -340,8 +919,8 @@ parse_binary_expression_node!(left_assoc, BitwiseOrExpression);
 
 binary_expression_node!(LogicalAndExpression, assoc: left, derive: BitwiseOrExpression, rhs: BitwiseOrExpression, operator: LogicalAndExpressionOp);
 
-#[derive(Copy, Clone)]
-enum LogicalAndExpressionOp {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum LogicalAndExpressionOp {
     LogicalAnd
 }
 
@@ -351,13 +930,198 @@ parse_binary_expression_node!(left_assoc, LogicalAndExpression);
 
 // ------------------------------------------------
 
-binary_expression_node!(LogicalOrExpression, assoc: left, derive: LogicalAndExpression, rhs: BitwiseAndExpression, operator: LogicalOrExpressionOp);
+binary_expression_node!(LogicalOrExpression, assoc: left, derive: LogicalAndExpression, rhs: LogicalAndExpression, operator: LogicalOrExpressionOp);
 
-#[derive(Copy, Clone)]
-enum LogicalOrExpressionOp {
+#[derive(Debug, Copy, Clone, IntoStaticStr)]
+pub enum LogicalOrExpressionOp {
     LogicalOr
 }
 
 operator_from_parser!(LogicalOrExpressionOp, PartPipePipe => LogicalOr);
 
 parse_binary_expression_node!(left_assoc, LogicalOrExpression);
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::{Lexer, Token};
+    use crate::compiler::parser::expression::{Additive, First, LogicalOrExpression, Multiplicative};
+    use crate::compiler::parser::{FromParser, Identifier, Parser, UnresolvedTypeName};
+    use crate::compiler::parser::visitor::{Visitable, Visitor};
+    use crate::compiler::stats::ProgramStats;
+    use crate::interpreter::{Interpreter, SupportedTypeBox};
+
+    /// writes the expression as an S-expression, e.g. `(Add a b)`.
+    #[derive(Default)]
+    struct Sexp(Vec<Vec<String>>);
+
+    impl Sexp {
+        fn push(&mut self, item: String) {
+            self.0.last_mut().expect("an expression has been entered").push(item);
+        }
+    }
+
+    impl Visitor for Sexp {
+        fn visit_identifier(&mut self, identifier: &Identifier) {
+            self.push(identifier.as_str().to_string());
+        }
+
+        fn visit_type_name(&mut self, type_name: &UnresolvedTypeName) {
+            let name = type_name.as_identifier().map_or("?", Identifier::as_str);
+            self.push(format!("(type {name})"));
+        }
+
+        fn visit_operator(&mut self, operator: &'static str) {
+            self.push(operator.to_string());
+        }
+
+        fn enter_expression(&mut self) {
+            self.0.push(vec![]);
+        }
+
+        fn leave_expression(&mut self) {
+            let items = self.0.pop().expect("an expression has been entered");
+            let node = if let [leaf] = items.as_slice() { leaf.clone() } else { format!("({})", items.join(" ")) };
+            match self.0.last_mut() {
+                Some(parent) => parent.push(node),
+                None => self.0.push(vec![node]),
+            }
+        }
+    }
+
+    fn sexp<T: FromParser + Visitable>(source: &str) -> String where T::Err: std::fmt::Debug {
+        let expression = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<T>().expect("the expression is valid");
+        let mut sexp = Sexp::default();
+        expression.accept(&mut sexp);
+        sexp.0.concat().join(" ")
+    }
+
+    #[test]
+    fn binary_expression_leaves_the_token_after_its_last_operand() {
+        let parser = Parser::with_lexer(Lexer::from_borrowed("a * b * c d"));
+        let expression = parser.parse::<Multiplicative>().expect("the expression is valid");
+        let mut stats = ProgramStats::default();
+        expression.accept(&mut stats);
+
+        assert_eq!(stats.operators.get("Multiply"), Some(&2));
+        assert_eq!(parser.lexer.peek().expect("the source can be lexed"), Token::Identifier { inner: "d".to_string() });
+    }
+
+    #[test]
+    fn additive_and_multiplicative_are_left_associative() {
+        assert_eq!(sexp::<Multiplicative>("a * b / c"), "(Divide (Multiply a b) c)");
+        assert_eq!(sexp::<Additive>("a - b - c"), "(Subtract (Subtract a b) c)");
+        assert_eq!(sexp::<Additive>("a - b * c - d"), "(Subtract (Subtract a (Multiply b c)) d)");
+    }
+
+    #[test]
+    fn percent_is_the_remainder_at_the_multiplicative_level() {
+        assert_eq!(sexp::<Multiplicative>("a % b"), "(Reminder a b)");
+        assert_eq!(sexp::<Additive>("a - b % c * d"), "(Subtract a (Multiply (Reminder b c) d))");
+    }
+
+    #[test]
+    fn parentheses_group_a_subtraction_under_a_multiplication() {
+        assert_eq!(sexp::<Multiplicative>("(a - b) * c"), "(Multiply (Subtract a b) c)");
+        assert_eq!(sexp::<Additive>("a - b * c"), "(Subtract a (Multiply b c))");
+
+        let expression = Parser::with_lexer(Lexer::from_borrowed("1 - (2 - 3)")).parse::<LogicalOrExpression>().expect("the expression is valid");
+        let value = Interpreter::new().evaluate(&expression).expect("the expression can be evaluated");
+        assert_eq!(value, SupportedTypeBox::I32(2));
+    }
+
+    #[test]
+    fn missing_closing_parenthesis_is_reported() {
+        let error = Parser::with_lexer(Lexer::from_borrowed("(1 + 2")).parse::<First>().expect_err("`)` is missing");
+
+        assert_eq!(error.to_string(), "EndOfFile is unexpected, expected `)` to close the parenthesized expression");
+        assert_eq!(error.span().start, 6);
+    }
+
+    #[test]
+    fn cast_binds_tighter_than_subtraction_and_parentheses_keep_it_inside() {
+        assert_eq!(sexp::<Additive>("(a as i32) - b"), "(Subtract (As a (type i32)) b)");
+        assert_eq!(sexp::<Additive>("a as i32 - b"), "(Subtract (As a (type i32)) b)");
+        assert_eq!(sexp::<Additive>("(a - b) as i64"), "(As (Subtract a b) (type i64))");
+    }
+
+    #[test]
+    fn logical_and_binds_tighter_than_logical_or() {
+        assert_eq!(sexp::<LogicalOrExpression>("x || y"), "(LogicalOr x y)");
+        assert_eq!(sexp::<LogicalOrExpression>("x && y"), "(LogicalAnd x y)");
+        assert_eq!(sexp::<LogicalOrExpression>("a && b || c && d"), "(LogicalOr (LogicalAnd a b) (LogicalAnd c d))");
+        assert_eq!(sexp::<LogicalOrExpression>("a || b || c"), "(LogicalOr (LogicalOr a b) c)");
+    }
+}
+
+// ------------------------------------------------
+
+/// right-associative
+/// e.g. `a ? b : c ? d : e` is equivalent with `a ? b : (c ? d : e)`.
+#[derive(Debug)]
+pub enum Conditional {
+    Do {
+        condition: Box<LogicalOrExpression>,
+        then: Box<Self>,
+        otherwise: Box<Self>,
+    },
+    Propagated(LogicalOrExpression),
+}
+
+impl Conditional {
+    /// returns the operand if this is just a [First], such as a literal or a variable.
+    pub fn as_first(&self) -> Option<&First> {
+        match self {
+            Conditional::Do { .. } => None,
+            Conditional::Propagated(inner) => inner.as_first(),
+        }
+    }
+
+    /// see [First::span].
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Conditional::Do { condition, otherwise, .. } => cover(condition.span(), otherwise.span()),
+            Conditional::Propagated(inner) => inner.span(),
+        }
+    }
+}
+
+impl FromParser for Conditional {
+    type Err = ParseError;
+
+    fn read(parser: &Parser) -> Result<Self, Self::Err> {
+        let condition = parser.parse()?;
+        if parser.lexer.peek()? != Token::SymQuestion {
+            return Ok(Self::Propagated(condition))
+        }
+
+        parser.lexer.next()?;
+        parser.skip_line_continuation()?;
+        // each branch is a conditional by itself, so a chain of them counts as nesting
+        let then = parser.nested(|parser| parser.parse())?;
+        parser.expect(Token::SymColon, "`:` in the conditional expression")?;
+        parser.skip_line_continuation()?;
+        let otherwise = parser.nested(|parser| parser.parse())?;
+
+        Ok(Self::Do {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise: Box::new(otherwise),
+        })
+    }
+}
+
+impl Visitable for Conditional {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            Conditional::Do { condition, then, otherwise } => {
+                visitor.enter_expression();
+                visitor.visit_operator("Conditional");
+                condition.accept(visitor);
+                then.accept(visitor);
+                otherwise.accept(visitor);
+                visitor.leave_expression();
+            }
+            Conditional::Propagated(inner) => inner.accept(visitor),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::compiler::parser::{Identifier, IdentifierOrMemberPath, MemberPath, RootAst, Statement, UnresolvedTypeName};
+
+/// Receives callbacks while an AST is walked in source order.
+/// Every hook does nothing by default, so implementors only override what they are interested in.
+pub trait Visitor {
+    fn visit_statement(&mut self, _statement: &Statement) {}
+
+    /// called after the children of the statement are walked.
+    fn leave_statement(&mut self, _statement: &Statement) {}
+
+    /// called before the statements of a block, such as the `then` branch of `if`, are walked.
+    fn enter_block(&mut self) {}
+
+    fn leave_block(&mut self) {}
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+
+    /// type names are not descended into, so identifiers inside of them are not reported.
+    fn visit_type_name(&mut self, _type_name: &UnresolvedTypeName) {}
+
+    /// `literal` is spelled as in the source, e.g. `10u8`, `true` or `"text"` with the quotes.
+    fn visit_literal(&mut self, _literal: &str) {}
+
+    /// `operator` is the name of the operator variant, e.g. `"Add"`.
+    fn visit_operator(&mut self, _operator: &'static str) {}
+
+    /// called before the children of an expression node are walked.
+    /// nodes which only propagate their child do not count.
+    fn enter_expression(&mut self) {}
+
+    fn leave_expression(&mut self) {}
+}
+
+pub trait Visitable {
+    fn accept<V: Visitor>(&self, visitor: &mut V);
+}
+
+impl<T: Visitable> Visitable for Option<T> {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        if let Some(inner) = self {
+            inner.accept(visitor);
+        }
+    }
+}
+
+impl<T: Visitable> Visitable for Box<T> {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        self.as_ref().accept(visitor);
+    }
+}
+
+impl Visitable for RootAst {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        for statement in &self.commands {
+            statement.accept(visitor);
+        }
+    }
+}
+
+impl Visitable for Statement {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit_statement(self);
+
+        match self {
+            Statement::NodeDeclaration { identifier, type_tag, rhs, .. } => {
+                identifier.accept(visitor);
+                type_tag.accept(visitor);
+                rhs.accept(visitor);
+            }
+            Statement::Comment { .. } => {}
+            Statement::Expression(expression) => expression.accept(visitor),
+            Statement::If { condition, then_block, elseif_branches, else_block } => {
+                condition.accept(visitor);
+                accept_block(then_block, visitor);
+                for branch in elseif_branches {
+                    branch.condition.accept(visitor);
+                    accept_block(&branch.block, visitor);
+                }
+                if let Some(else_block) = else_block {
+                    accept_block(else_block, visitor);
+                }
+            }
+            Statement::While { condition, body } => {
+                condition.accept(visitor);
+                accept_block(body, visitor);
+            }
+            Statement::Assignment { target, rhs } => {
+                target.accept(visitor);
+                rhs.accept(visitor);
+            }
+            Statement::For { variable, from, to, body } => {
+                variable.accept(visitor);
+                from.accept(visitor);
+                to.accept(visitor);
+                accept_block(body, visitor);
+            }
+            Statement::NoMoreStatements => {}
+        }
+
+        visitor.leave_statement(self);
+    }
+}
+
+fn accept_block<V: Visitor>(block: &[Statement], visitor: &mut V) {
+    visitor.enter_block();
+    for statement in block {
+        statement.accept(visitor);
+    }
+    visitor.leave_block();
+}
+
+impl Visitable for Identifier {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit_identifier(self);
+    }
+}
+
+impl Visitable for UnresolvedTypeName {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit_type_name(self);
+    }
+}
+
+impl Visitable for IdentifierOrMemberPath {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            IdentifierOrMemberPath::Identifier(identifier) => identifier.accept(visitor),
+            IdentifierOrMemberPath::MemberPath(member_path) => member_path.accept(visitor),
+        }
+    }
+}
+
+impl Visitable for MemberPath {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        for identifier in &self.pack {
+            identifier.accept(visitor);
+        }
+    }
+}
@@ -1,4 +1,11 @@
-enum TypeTag {
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use anyhow::{anyhow, bail};
+use serde::Serialize;
+use crate::compiler::parser::{IdentifierOrMemberPath, UnresolvedTypeName};
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize)]
+pub enum TypeTag {
     I8,
     U8,
     I16,
@@ -56,13 +63,15 @@ enum TypeTag {
     }
 }
 
-enum MatrixElementCount {
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize)]
+pub enum MatrixElementCount {
     Two,
     Three,
     Four,
 }
 
-enum Matrix1DTypeTag {
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize)]
+pub enum Matrix1DTypeTag {
     Bool,
     F64,
     F32,
@@ -72,7 +81,284 @@ enum Matrix1DTypeTag {
     U64,
 }
 
-enum Matrix2DTypeTag {
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize)]
+pub enum Matrix2DTypeTag {
     F64,
     F32,
 }
+
+/// the spellings of the types which neither have type parameters nor are vectors or matrices.
+static PRIMITIVES: [(&str, TypeTag); 33] = [
+    ("i8", TypeTag::I8),
+    ("u8", TypeTag::U8),
+    ("i16", TypeTag::I16),
+    ("u16", TypeTag::U16),
+    ("i32", TypeTag::I32),
+    ("u32", TypeTag::U32),
+    ("i64", TypeTag::I64),
+    ("u64", TypeTag::U64),
+    ("f32", TypeTag::F32),
+    ("f64", TypeTag::F64),
+    ("string", TypeTag::String),
+    ("impulse", TypeTag::Impulse),
+    ("bool", TypeTag::Bool),
+    ("BoundingBox", TypeTag::BoundingBox),
+    ("c16", TypeTag::Char16),
+    ("c32", TypeTag::Char32),
+    ("color", TypeTag::Color),
+    ("DateTime", TypeTag::DateTime),
+    ("decimal", TypeTag::Decimal),
+    ("doubleQ", TypeTag::DoubleQuotanion),
+    ("floatQ", TypeTag::FloatQuotanion),
+    ("Slot", TypeTag::Slot),
+    ("User", TypeTag::User),
+    ("SyncPlayback", TypeTag::SyncPlayback),
+    ("IWorldElement", TypeTag::IWorldElement),
+    ("Uri", TypeTag::Uri),
+    ("StaticAudioClipProvider", TypeTag::StaticAudioClipProvider),
+    ("StaticMesh", TypeTag::StaticMesh),
+    ("SpriteProvider", TypeTag::SpriteProvider),
+    ("StaticTexture2D", TypeTag::StaticTexture2D),
+    ("AvatarAnchor", TypeTag::AvatarAnchor),
+    ("IFingerPoseSource", TypeTag::IFingerPoseSource),
+    ("IComponent", TypeTag::IComponent),
+];
+
+impl TypeTag {
+    /// resolves a type name that has no type parameters. returns `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRIMITIVES.iter()
+            .find(|(spelling, _)| *spelling == name)
+            .map(|(_, tag)| tag.clone())
+            .or_else(|| Self::from_matrix_name(name))
+    }
+
+    /// resolves vectors such as `float3`, and square matrices such as `float4x4`.
+    /// matrices can only hold `float` or `double`.
+    fn from_matrix_name(name: &str) -> Option<Self> {
+        let split_at = name.find(|c: char| c.is_ascii_digit())?;
+        let (element, dimensions) = name.split_at(split_at);
+        let element_count = |count: &str| match count {
+            "2" => Some(MatrixElementCount::Two),
+            "3" => Some(MatrixElementCount::Three),
+            "4" => Some(MatrixElementCount::Four),
+            _ => None,
+        };
+        // keep in sync with Matrix1DTypeTag::name and Matrix2DTypeTag::name
+
+        match dimensions.split_once('x') {
+            None => {
+                let type_tag = match element {
+                    "bool" => Matrix1DTypeTag::Bool,
+                    "double" => Matrix1DTypeTag::F64,
+                    "float" => Matrix1DTypeTag::F32,
+                    "int" => Matrix1DTypeTag::I32,
+                    "long" => Matrix1DTypeTag::I64,
+                    "uint" => Matrix1DTypeTag::U32,
+                    "ulong" => Matrix1DTypeTag::U64,
+                    _ => return None,
+                };
+
+                Some(TypeTag::Matrix1D {
+                    element_count: element_count(dimensions)?,
+                    type_tag,
+                })
+            }
+            Some((rows, columns)) => {
+                if rows != columns {
+                    return None
+                }
+
+                let type_tag = match element {
+                    "double" => Matrix2DTypeTag::F64,
+                    "float" => Matrix2DTypeTag::F32,
+                    _ => return None,
+                };
+
+                Some(TypeTag::Matrix2D {
+                    element_count: element_count(rows)?,
+                    type_tag,
+                })
+            }
+        }
+    }
+
+    pub fn is_integral(&self) -> bool {
+        matches!(self, TypeTag::I8 | TypeTag::U8 | TypeTag::I16 | TypeTag::U16 | TypeTag::I32 | TypeTag::U32 | TypeTag::I64 | TypeTag::U64)
+    }
+
+    /// whether the digits of an integral literal, such as `-1`, are in the range of this type.
+    /// always false if this is not an integral type.
+    pub fn can_hold(&self, sequence: &str) -> bool {
+        match self {
+            TypeTag::I8 => sequence.parse::<i8>().is_ok(),
+            TypeTag::U8 => sequence.parse::<u8>().is_ok(),
+            TypeTag::I16 => sequence.parse::<i16>().is_ok(),
+            TypeTag::U16 => sequence.parse::<u16>().is_ok(),
+            TypeTag::I32 => sequence.parse::<i32>().is_ok(),
+            TypeTag::U32 => sequence.parse::<u32>().is_ok(),
+            TypeTag::I64 => sequence.parse::<i64>().is_ok(),
+            TypeTag::U64 => sequence.parse::<u64>().is_ok(),
+            _ => false,
+        }
+    }
+
+    pub fn resolve(type_name: &UnresolvedTypeName) -> Option<Self> {
+        if let Some(identifier) = type_name.as_identifier() {
+            return Self::from_name(identifier.as_str())
+        }
+
+        let IdentifierOrMemberPath::Identifier(identifier) = type_name.name() else {
+            return None
+        };
+        let [argument] = type_name.arguments() else {
+            return None
+        };
+        let inner = Box::new(Self::resolve(argument)?);
+        let tag = match identifier.as_str() {
+            "IValue" => TypeTag::IValue { inner },
+            "IField" => TypeTag::IField { inner },
+            "SyncRef" => TypeTag::SyncRef { inner },
+            "IAssetProvider" => TypeTag::IAssetProvider { inner },
+            _ => return None,
+        };
+
+        Some(tag)
+    }
+}
+
+impl MatrixElementCount {
+    fn count(self) -> usize {
+        match self {
+            MatrixElementCount::Two => 2,
+            MatrixElementCount::Three => 3,
+            MatrixElementCount::Four => 4,
+        }
+    }
+}
+
+impl Matrix1DTypeTag {
+    fn name(self) -> &'static str {
+        match self {
+            Matrix1DTypeTag::Bool => "bool",
+            Matrix1DTypeTag::F64 => "double",
+            Matrix1DTypeTag::F32 => "float",
+            Matrix1DTypeTag::I32 => "int",
+            Matrix1DTypeTag::I64 => "long",
+            Matrix1DTypeTag::U32 => "uint",
+            Matrix1DTypeTag::U64 => "ulong",
+        }
+    }
+}
+
+impl Matrix2DTypeTag {
+    fn name(self) -> &'static str {
+        match self {
+            Matrix2DTypeTag::F64 => "double",
+            Matrix2DTypeTag::F32 => "float",
+        }
+    }
+}
+
+impl Display for TypeTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeTag::IValue { inner } => write!(f, "IValue<{inner}>"),
+            TypeTag::IField { inner } => write!(f, "IField<{inner}>"),
+            TypeTag::SyncRef { inner } => write!(f, "SyncRef<{inner}>"),
+            TypeTag::IAssetProvider { inner } => write!(f, "IAssetProvider<{inner}>"),
+            TypeTag::ToBeInferred => write!(f, "dummy"),
+            TypeTag::Matrix1D { element_count, type_tag } => {
+                write!(f, "{name}{count}", name = type_tag.name(), count = element_count.count())
+            }
+            TypeTag::Matrix2D { element_count, type_tag } => {
+                let count = element_count.count();
+                write!(f, "{name}{count}x{count}", name = type_tag.name())
+            }
+            primitive => {
+                let (spelling, _) = PRIMITIVES.iter()
+                    .find(|(_, tag)| tag == primitive)
+                    .expect("every other variant is listed in PRIMITIVES");
+                write!(f, "{spelling}")
+            }
+        }
+    }
+}
+
+impl FromStr for TypeTag {
+    type Err = anyhow::Error;
+
+    /// accepts what [Display] produces, except for `dummy`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, argument)) = s.strip_suffix('>').and_then(|s| s.split_once('<')) else {
+            return Self::from_name(s).ok_or_else(|| anyhow!("unknown type: {s}"))
+        };
+
+        let inner = Box::new(argument.trim().parse()?);
+        let tag = match name {
+            "IValue" => TypeTag::IValue { inner },
+            "IField" => TypeTag::IField { inner },
+            "SyncRef" => TypeTag::SyncRef { inner },
+            "IAssetProvider" => TypeTag::IAssetProvider { inner },
+            _ => bail!("unknown generic type: {name}"),
+        };
+
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::resolved_type_tag::{Matrix1DTypeTag, Matrix2DTypeTag, MatrixElementCount, PRIMITIVES, TypeTag};
+
+    fn every_tag() -> Vec<TypeTag> {
+        let counts = [MatrixElementCount::Two, MatrixElementCount::Three, MatrixElementCount::Four];
+        let vectors = [
+            Matrix1DTypeTag::Bool, Matrix1DTypeTag::F64, Matrix1DTypeTag::F32, Matrix1DTypeTag::I32,
+            Matrix1DTypeTag::I64, Matrix1DTypeTag::U32, Matrix1DTypeTag::U64,
+        ];
+        let matrices = [Matrix2DTypeTag::F64, Matrix2DTypeTag::F32];
+
+        let mut tags: Vec<_> = PRIMITIVES.iter().map(|(_, tag)| tag.clone()).collect();
+        for element_count in counts {
+            tags.extend(vectors.iter().map(|&type_tag| TypeTag::Matrix1D { element_count, type_tag }));
+            tags.extend(matrices.iter().map(|&type_tag| TypeTag::Matrix2D { element_count, type_tag }));
+        }
+        let inner = || Box::new(TypeTag::I32);
+        tags.extend([
+            TypeTag::IValue { inner: inner() },
+            TypeTag::IField { inner: inner() },
+            TypeTag::SyncRef { inner: inner() },
+            TypeTag::IAssetProvider { inner: inner() },
+            TypeTag::IValue { inner: Box::new(TypeTag::SyncRef { inner: Box::new(TypeTag::Slot) }) },
+        ]);
+        tags
+    }
+
+    #[test]
+    fn display_round_trips_through_from_str() {
+        for tag in every_tag() {
+            let spelling = tag.to_string();
+            assert_eq!(spelling.parse::<TypeTag>().ok(), Some(tag), "{spelling} did not round-trip");
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        for name in ["int", "float5", "float2x3", "int4x4", "Foo<i32>", "IValue<Foo>", "IValue<>", "dummy", ""] {
+            assert!(name.parse::<TypeTag>().is_err(), "{name} was accepted");
+        }
+    }
+
+    #[test]
+    fn vector_and_square_matrix_names_are_resolved() {
+        assert_eq!(TypeTag::from_name("float3"), Some(TypeTag::Matrix1D { element_count: MatrixElementCount::Three, type_tag: Matrix1DTypeTag::F32 }));
+        assert_eq!(TypeTag::from_name("ulong2"), Some(TypeTag::Matrix1D { element_count: MatrixElementCount::Two, type_tag: Matrix1DTypeTag::U64 }));
+        assert_eq!(TypeTag::from_name("double4x4"), Some(TypeTag::Matrix2D { element_count: MatrixElementCount::Four, type_tag: Matrix2DTypeTag::F64 }));
+
+        // only square matrices of float or double, with 2 to 4 elements on each side
+        for name in ["float1", "float5", "float2x3", "int2x2", "bool3x3", "float", "3"] {
+            assert_eq!(TypeTag::from_name(name), None, "{name}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use serde::Serialize;
+
+/// A range in the source, counted in chars. `end` is exclusive.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// the range from the start of this span to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+/// Resolves char offsets, such as [Span::start], into lines and columns.
+/// built once per source, then each lookup is a binary search over the line starts.
+pub struct LineIndex {
+    /// the char offset where each line begins
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.chars().enumerate().filter(|(_, c)| *c == '\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        Self {
+            line_starts
+        }
+    }
+
+    /// returns the 1-origin line and column of the char offset. a `\n` belongs to the line it terminates.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let column = offset - self.line_starts[line - 1] + 1;
+
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::span::LineIndex;
+
+    #[test]
+    fn offset_is_resolved_into_a_line_and_a_column_in_chars() {
+        let index = LineIndex::new("ab\né\n\nx");
+
+        assert_eq!(index.line_col(0), (1, 1));
+        // the `\n` belongs to the line it terminates
+        assert_eq!(index.line_col(2), (1, 3));
+        assert_eq!(index.line_col(4), (2, 2));
+        assert_eq!(index.line_col(5), (3, 1));
+        assert_eq!(index.line_col(6), (4, 1));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use crate::compiler::parser::{Identifier, RootAst, Statement};
+use crate::compiler::parser::visitor::{Visitable, Visitor};
+
+/// Summary of a parsed program, shown by the `stats` subcommand.
+#[derive(Default, Debug, Eq, PartialEq)]
+pub struct ProgramStats {
+    pub statements: usize,
+    pub declarations: usize,
+    pub identifiers: BTreeSet<String>,
+    /// keyed by operator name, e.g. `"Add"`.
+    pub operators: BTreeMap<&'static str, usize>,
+    pub max_expression_depth: usize,
+    current_expression_depth: usize,
+}
+
+impl ProgramStats {
+    pub fn collect(root: &RootAst) -> Self {
+        let mut stats = Self::default();
+        root.accept(&mut stats);
+        stats
+    }
+}
+
+impl Visitor for ProgramStats {
+    fn visit_statement(&mut self, statement: &Statement) {
+        self.statements += 1;
+        if let Statement::NodeDeclaration { .. } = statement {
+            self.declarations += 1;
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.identifiers.insert(identifier.as_str().to_string());
+    }
+
+    fn visit_operator(&mut self, operator: &'static str) {
+        *self.operators.entry(operator).or_default() += 1;
+    }
+
+    fn enter_expression(&mut self) {
+        self.current_expression_depth += 1;
+        self.max_expression_depth = self.max_expression_depth.max(self.current_expression_depth);
+    }
+
+    fn leave_expression(&mut self) {
+        self.current_expression_depth -= 1;
+    }
+}
+
+impl Display for ProgramStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "statements: {}", self.statements)?;
+        writeln!(f, "declarations: {}", self.declarations)?;
+        writeln!(f, "distinct identifiers: {}", self.identifiers.len())?;
+        writeln!(f, "max expression depth: {}", self.max_expression_depth)?;
+        for (operator, count) in &self.operators {
+            writeln!(f, "operator {operator}: {count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::stats::ProgramStats;
+
+    fn collect(source: &str) -> ProgramStats {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        ProgramStats::collect(&root)
+    }
+
+    #[test]
+    fn counts_declarations_identifiers_and_operators() {
+        let stats = collect("var a = 1\nvar b = a + 2\nb * (a - 1)\n");
+
+        assert_eq!(stats.statements, 3);
+        assert_eq!(stats.declarations, 2);
+        assert_eq!(stats.identifiers.iter().map(String::as_str).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(stats.operators.get("Add"), Some(&1));
+        assert_eq!(stats.operators.get("Multiply"), Some(&1));
+        assert_eq!(stats.operators.get("Subtract"), Some(&1));
+    }
+
+    #[test]
+    fn report_lists_every_count() {
+        assert_eq!(
+            collect("var a = 1 + 2\n").to_string(),
+            "statements: 1\ndeclarations: 1\ndistinct identifiers: 1\nmax expression depth: 2\noperator Add: 1\n"
+        );
+    }
+
+    #[test]
+    fn depth_counts_operators_and_operands_but_not_parentheses() {
+        assert_eq!(collect("1\n").max_expression_depth, 1);
+        assert_eq!(collect("((1))\n").max_expression_depth, 1);
+        assert_eq!(collect("1 + (2 * (3 - 4))\n").max_expression_depth, 4);
+    }
+}
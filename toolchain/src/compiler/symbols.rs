@@ -0,0 +1,99 @@
+use std::fmt::{Display, Formatter};
+use crate::compiler::parser::{RootAst, Statement};
+use crate::compiler::parser::visitor::{Visitable, Visitor};
+use crate::compiler::resolved_type_tag::TypeTag;
+use crate::compiler::span::{LineIndex, Span};
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// `None` if the annotated type could not be resolved.
+    /// declarations without annotation get [TypeTag::ToBeInferred].
+    pub type_tag: Option<TypeTag>,
+    pub span: Span,
+    /// 1-origin
+    pub line: usize,
+    /// 1-origin, counted in chars
+    pub column: usize,
+}
+
+/// Every declared symbol in source order, shown by the `dump-symbols` subcommand.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+}
+
+struct SymbolCollector {
+    line_index: LineIndex,
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn collect(source: &str, root: &RootAst) -> Self {
+        let mut collector = SymbolCollector {
+            line_index: LineIndex::new(source),
+            symbols: vec![],
+        };
+        root.accept(&mut collector);
+
+        Self {
+            symbols: collector.symbols
+        }
+    }
+}
+
+impl Visitor for SymbolCollector {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::NodeDeclaration { identifier, type_tag, .. } = statement {
+            let type_tag = match type_tag {
+                Some(type_name) => TypeTag::resolve(type_name),
+                None => Some(TypeTag::ToBeInferred),
+            };
+            let span = identifier.span();
+            let (line, column) = self.line_index.line_col(span.start);
+
+            self.symbols.push(Symbol {
+                name: identifier.as_str().to_string(),
+                type_tag,
+                span,
+                line,
+                column,
+            });
+        }
+    }
+}
+
+impl Display for SymbolTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for symbol in &self.symbols {
+            match &symbol.type_tag {
+                // shown as `dummy` otherwise
+                Some(TypeTag::ToBeInferred) => write!(f, "{}: <inferred>", symbol.name)?,
+                Some(type_tag) => write!(f, "{}: {type_tag}", symbol.name)?,
+                None => write!(f, "{}: <unresolved>", symbol.name)?,
+            }
+            writeln!(f, " at {}:{}", symbol.line, symbol.column)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::symbols::SymbolTable;
+
+    fn collect(source: &str) -> SymbolTable {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        SymbolTable::collect(source, &root)
+    }
+
+    #[test]
+    fn declarations_are_listed_in_source_order_including_nested_ones() {
+        let table = collect("var a = 1\nvar b: i64 = 2\nif true then\n  var d: Nope = 1.5\nend\n");
+
+        assert_eq!(table.to_string(), "a: <inferred> at 1:5\nb: i64 at 2:5\nd: <unresolved> at 4:7\n");
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt::Write;
+use serde::Serialize;
+use crate::compiler::lexer::{LexError, Lexer, Token};
+use crate::compiler::span::Span;
+
+#[derive(Serialize)]
+struct SpannedToken {
+    token: Token,
+    span: Span,
+}
+
+/// lexes the rest of the source up to and including [Token::EndOfFile].
+fn lex_all(lexer: &Lexer) -> Result<Vec<SpannedToken>, LexError> {
+    let mut tokens = vec![];
+    loop {
+        let (token, span) = lexer.next_spanned()?;
+        let reached_end = token == Token::EndOfFile;
+        tokens.push(SpannedToken { token, span });
+        if reached_end {
+            break
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// prints each token on its own line, after its span, such as `4..5 Identifier { inner: "a" }`.
+pub fn dump(lexer: &Lexer) -> Result<String, LexError> {
+    let mut out = String::new();
+    for SpannedToken { token, span } in lex_all(lexer)? {
+        writeln!(out, "{start}..{end} {token:?}", start = span.start, end = span.end).expect("writing to a String never fails");
+    }
+
+    Ok(out)
+}
+
+/// prints the tokens as a JSON array of `{"token": ..., "span": {"start": ..., "end": ...}}`.
+pub fn dump_json(lexer: &Lexer) -> Result<String, LexError> {
+    let tokens = lex_all(lexer)?;
+    Ok(serde_json::to_string_pretty(&tokens).expect("failed to serialize") + "\n")
+}
@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use crate::compiler::parser::{Identifier, IdentifierOrMemberPath, RootAst, Statement, UnresolvedTypeName};
+use crate::compiler::parser::expression::{
+    Additive, AdditiveOps, BitwiseAndExpression, BitwiseOrExpression, BitwiseShift, BitwiseXorExpression, Cast,
+    Conditional, EqualityCheckExpression, Expression, First, LogicalAndExpression, LogicalOrExpression, MatchPattern,
+    Multiplicative, MultiplicativeOps, RelationCheckExpression, RelationCheckExpressionOps, Unary, UnaryOps,
+};
+use crate::compiler::resolved_type_tag::TypeTag;
+use crate::compiler::span::Span;
+
+/// the types of the variables which an expression can refer to.
+pub struct TypeEnv {
+    variables: HashMap<String, TypeTag>,
+    /// the type of unsuffixed integral literals
+    default_int: TypeTag,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        Self {
+            variables: HashMap::new(),
+            default_int: TypeTag::I32,
+        }
+    }
+
+    pub fn with_default_int(self, default_int: TypeTag) -> Self {
+        assert!(default_int.is_integral(), "{default_int} is not an integral type");
+
+        Self {
+            default_int,
+            ..self
+        }
+    }
+
+    /// shadows the previous type if the name has already been declared.
+    pub fn declare(&mut self, name: &str, type_tag: TypeTag) {
+        self.variables.insert(name.to_string(), type_tag);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TypeTag> {
+        self.variables.get(name)
+    }
+}
+
+impl Default for TypeEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Why an expression is not well-typed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TypeError {
+    pub message: String,
+    /// where the offending expression is, as far as its literals and identifiers tell.
+    pub span: Option<Span>,
+}
+
+impl TypeError {
+    fn new(message: String, span: Option<Span>) -> Self {
+        Self {
+            message,
+            span,
+        }
+    }
+}
+
+/// only what is wrong. [Diagnostic](crate::compiler::diagnostic::Diagnostic) tells where it is, in lines and columns.
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// infers the type of the expression without evaluating it.
+/// the rules are the ones of the interpreter without numeric promotion, so both operands of a binary operator must have the same type.
+pub fn type_of(expression: &Expression, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+    expression.type_of(scope)
+}
+
+/// checks the statements in order, as the interpreter runs them. a declaration brings its name into `scope`
+/// with the annotated type, or with the type of its value if it is not annotated.
+/// the type of a member cannot be inferred yet, so a declaration such as `var a = b.c` is trusted.
+pub fn check_program(root: &RootAst, scope: &mut TypeEnv) -> Result<(), TypeError> {
+    check_block(root.commands(), scope)
+}
+
+/// blocks do not introduce their own scope, as in the interpreter.
+fn check_block(block: &[Statement], scope: &mut TypeEnv) -> Result<(), TypeError> {
+    for statement in block {
+        match statement {
+            Statement::NodeDeclaration { identifier, type_tag, rhs, .. } => {
+                let type_tag = match type_tag {
+                    Some(type_name) => {
+                        let declared = resolve(type_name)?;
+                        check_assignable(identifier.as_str(), rhs, &declared, scope)?;
+                        declared
+                    }
+                    None if matches!(rhs.as_first(), Some(First::MemberPath { .. })) => TypeTag::ToBeInferred,
+                    None => rhs.type_of(scope)?,
+                };
+                scope.declare(identifier.as_str(), type_tag);
+            }
+            Statement::Assignment { target: IdentifierOrMemberPath::Identifier(identifier), rhs } => {
+                let name = identifier.as_str();
+                let into = scope.get(name)
+                    .cloned()
+                    .ok_or_else(|| TypeError::new(format!("`{name}` is assigned before its declaration"), Some(identifier.span())))?;
+                check_assignable(name, rhs, &into, scope)?;
+            }
+            Statement::Assignment { target: IdentifierOrMemberPath::MemberPath(_), rhs } | Statement::Expression(rhs) => {
+                rhs.type_of(scope)?;
+            }
+            Statement::If { condition, then_block, elseif_branches, else_block } => {
+                check_condition(condition, scope)?;
+                check_block(then_block, scope)?;
+                for branch in elseif_branches {
+                    check_condition(&branch.condition, scope)?;
+                    check_block(&branch.block, scope)?;
+                }
+                if let Some(else_block) = else_block {
+                    check_block(else_block, scope)?;
+                }
+            }
+            Statement::While { condition, body } => {
+                check_condition(condition, scope)?;
+                check_block(body, scope)?;
+            }
+            Statement::For { variable, from, to, body } => {
+                let from_type = from.type_of(scope)?;
+                let to_type = to.type_of(scope)?;
+                if !from_type.is_integral() || from_type != to_type {
+                    return Err(TypeError::new(
+                        format!("the bounds of a for loop must be integers of the same type, but they were {from_type} and {to_type}"),
+                        from.span().or_else(|| to.span()),
+                    ))
+                }
+
+                // the loop variable is only visible inside of the body
+                let shadowed = scope.variables.remove(variable.as_str());
+                scope.declare(variable.as_str(), from_type);
+                let checked = check_block(body, scope);
+                scope.variables.remove(variable.as_str());
+                if let Some(shadowed) = shadowed {
+                    scope.declare(variable.as_str(), shadowed);
+                }
+                checked?;
+            }
+            Statement::Comment { .. } | Statement::NoMoreStatements => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn resolve(type_name: &UnresolvedTypeName) -> Result<TypeTag, TypeError> {
+    TypeTag::resolve(type_name)
+        .ok_or_else(|| TypeError::new(format!("`{type_name}` is not a type"), type_name.as_identifier().map(Identifier::span)))
+}
+
+fn check_condition(condition: &Expression, scope: &TypeEnv) -> Result<(), TypeError> {
+    let condition_type = condition.type_of(scope)?;
+    if condition_type != TypeTag::Bool {
+        return Err(TypeError::new(format!("the condition must be bool, but it is {condition_type}"), condition.span()))
+    }
+
+    Ok(())
+}
+
+/// whether a value of `rhs` can be stored into `name`, which is `into`. an unsuffixed literal takes the type of the variable,
+/// so `var x: i8 = 1` and `var x: f64 = 0.5` are fine. otherwise, only the safe casts in the spec are done implicitly.
+fn check_assignable(name: &str, rhs: &Expression, into: &TypeTag, scope: &TypeEnv) -> Result<(), TypeError> {
+    match rhs.as_first() {
+        Some(First::IntegralLiteral { suffix: None, .. }) if is_numeric(into) => return Ok(()),
+        Some(First::FloatLiteral { .. }) if matches!(into, TypeTag::F32 | TypeTag::F64) => return Ok(()),
+        Some(First::MemberPath { .. }) => return Ok(()),
+        _ => {}
+    }
+
+    let from = rhs.type_of(scope)?;
+    if from != *into && !widens(&from, into) {
+        return Err(TypeError::new(format!("`{name}` is {into}, so {from} cannot be assigned to it"), rhs.span()))
+    }
+
+    Ok(())
+}
+
+/// the implicit conversions of the interpreter, which never lose the value.
+fn widens(from: &TypeTag, into: &TypeTag) -> bool {
+    use TypeTag::*;
+
+    matches!(
+        (from, into),
+        (I8, I16 | I32 | I64 | F32 | F64)
+            | (I16, I32 | I64 | F32 | F64)
+            | (I32, I64 | F64)
+            | (U8, U16 | U32 | U64 | I16 | I32 | I64 | F32 | F64)
+            | (U16, U32 | U64 | I32 | I64 | F32 | F64)
+            | (U32, U64 | I64 | F64)
+    )
+}
+
+trait TypeOf {
+    fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError>;
+}
+
+fn is_numeric(type_tag: &TypeTag) -> bool {
+    type_tag.is_integral() || matches!(type_tag, TypeTag::F32 | TypeTag::F64)
+}
+
+fn is_signed(type_tag: &TypeTag) -> bool {
+    matches!(type_tag, TypeTag::I8 | TypeTag::I16 | TypeTag::I32 | TypeTag::I64 | TypeTag::F32 | TypeTag::F64)
+}
+
+impl TypeOf for First {
+    fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+        match self {
+            First::IntegralLiteral { suffix: Some(suffix), .. } => TypeTag::from_name(suffix)
+                .ok_or_else(|| TypeError::new(format!("`{suffix}` is not a type"), self.span())),
+            First::IntegralLiteral { suffix: None, .. } => Ok(scope.default_int.clone()),
+            First::FloatLiteral { .. } => Ok(TypeTag::F32),
+            First::StringLiteral { .. } => Ok(TypeTag::String),
+            First::True { .. } | First::False { .. } => Ok(TypeTag::Bool),
+            First::Variable { identifier } => scope.get(identifier.as_str())
+                .cloned()
+                .ok_or_else(|| TypeError::new(format!("`{identifier}` is not declared", identifier = identifier.as_str()), Some(identifier.span()))),
+            First::MemberPath { path } => Err(TypeError::new(format!("the type of `{path}` cannot be inferred yet"), self.span())),
+            First::Call { callee, args } => {
+                let args = args.iter().map(|arg| arg.type_of(scope)).collect::<Result<Vec<_>, _>>()?;
+                match (callee.as_str(), args.as_slice()) {
+                    ("len", [TypeTag::String]) => Ok(TypeTag::I64),
+                    ("len", [other]) => Err(TypeError::new(format!("len is not defined for {other}"), Some(callee.span()))),
+                    ("len", _) => Err(TypeError::new(format!("len takes 1 argument, but {count} were given", count = args.len()), Some(callee.span()))),
+                    (name, _) => Err(TypeError::new(format!("{name} is not a builtin function"), Some(callee.span()))),
+                }
+            }
+            First::Match { scrutinee, arms } => {
+                let scrutinee_type = scrutinee.type_of(scope)?;
+                let mut result = None;
+                for arm in arms {
+                    if let MatchPattern::Literal(pattern) = &arm.pattern {
+                        let pattern_type = pattern.type_of(scope)?;
+                        if pattern_type != scrutinee_type {
+                            return Err(TypeError::new(format!("the pattern is {pattern_type}, but the scrutinee is {scrutinee_type}"), scrutinee.span()))
+                        }
+                    }
+
+                    let value_type = arm.value.type_of(scope)?;
+                    match &result {
+                        Some(expected) if *expected != value_type => {
+                            return Err(TypeError::new(format!("the arms of `match` must have the same type, but found {expected} and {value_type}"), arm.value.span()))
+                        }
+                        _ => result = Some(value_type),
+                    }
+                }
+
+                result.ok_or_else(|| TypeError::new("`match` has no arms".to_string(), scrutinee.span()))
+            }
+            First::List { .. } | First::Index { .. } => Err(TypeError::new("the type of a list cannot be inferred yet".to_string(), self.span())),
+            First::Parenthesized(inner) => inner.type_of(scope),
+        }
+    }
+}
+
+impl TypeOf for Cast {
+    fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+        match self {
+            Cast::Do { operand, tp } => {
+                let from = operand.type_of(scope)?;
+                let into = TypeTag::resolve(tp)
+                    .ok_or_else(|| TypeError::new(format!("`{tp}` is not a type"), tp.as_identifier().map(|identifier| identifier.span())))?;
+
+                if from == into || (is_numeric(&from) && is_numeric(&into)) {
+                    Ok(into)
+                } else {
+                    Err(TypeError::new(format!("{from} cannot be casted to {into}"), operand.span()))
+                }
+            }
+            Cast::Propagated(first) => first.type_of(scope),
+        }
+    }
+}
+
+impl TypeOf for Unary {
+    fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+        match self {
+            Unary::Do { operator, operand } => {
+                let operand_type = operand.type_of(scope)?;
+                let allowed = match operator {
+                    UnaryOps::Negate => is_signed(&operand_type),
+                    UnaryOps::LogicalNot => operand_type == TypeTag::Bool,
+                    UnaryOps::BitwiseNot => operand_type.is_integral(),
+                };
+
+                if allowed {
+                    Ok(operand_type)
+                } else {
+                    Err(TypeError::new(format!(
+                        "{operator} cannot be applied to {operand_type}",
+                        operator = <&'static str>::from(operator)
+                    ), operand.span()))
+                }
+            }
+            Unary::Propagated(cast) => cast.type_of(scope),
+        }
+    }
+}
+
+/// implements [TypeOf] for a binary node. `$result` gets the operator and the types of the operands,
+/// and returns the type of the result, or `None` if the operator does not accept them.
+macro_rules! type_of_binary {
+    ($name:ident, $result:expr) => {
+        impl TypeOf for $name {
+            fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+                match self {
+                    $name::Binary { operator, lhs, rhs } => {
+                        let lhs_type = lhs.type_of(scope)?;
+                        let rhs_type = rhs.type_of(scope)?;
+                        let result: fn(_, &TypeTag, &TypeTag) -> Option<TypeTag> = $result;
+
+                        result(*operator, &lhs_type, &rhs_type).ok_or_else(|| TypeError::new(format!(
+                            "{operator} cannot be applied to {lhs_type} and {rhs_type}",
+                            operator = <&'static str>::from(operator)
+                        ), self.span()))
+                    }
+                    $name::Propagated(inner) => inner.type_of(scope),
+                }
+            }
+        }
+    };
+}
+
+/// the common type of the operands, if they have the same type and it satisfies the predicate.
+fn same_type(lhs: &TypeTag, rhs: &TypeTag, predicate: fn(&TypeTag) -> bool) -> Option<TypeTag> {
+    (lhs == rhs && predicate(lhs)).then(|| lhs.clone())
+}
+
+type_of_binary!(Multiplicative, |operator, lhs, rhs| match (operator, lhs, rhs) {
+    // repeats the string
+    (MultiplicativeOps::Multiply, TypeTag::String, TypeTag::I32) => Some(TypeTag::String),
+    _ => same_type(lhs, rhs, is_numeric),
+});
+
+type_of_binary!(Additive, |operator, lhs, rhs| match (operator, lhs, rhs) {
+    (AdditiveOps::Add, TypeTag::String, TypeTag::String) => Some(TypeTag::String),
+    _ => same_type(lhs, rhs, is_numeric),
+});
+
+// the shift amount may be any integral type
+type_of_binary!(BitwiseShift, |_, lhs, rhs| (lhs.is_integral() && rhs.is_integral()).then(|| lhs.clone()));
+
+type_of_binary!(RelationCheckExpression, |operator, lhs, rhs| {
+    same_type(lhs, rhs, is_numeric).map(|_| match operator {
+        RelationCheckExpressionOps::Spaceship => TypeTag::I32,
+        _ => TypeTag::Bool,
+    })
+});
+
+type_of_binary!(EqualityCheckExpression, |_, lhs, rhs| (lhs == rhs).then_some(TypeTag::Bool));
+
+type_of_binary!(BitwiseAndExpression, |_, lhs, rhs| same_type(lhs, rhs, |t| t.is_integral() || *t == TypeTag::Bool));
+type_of_binary!(BitwiseXorExpression, |_, lhs, rhs| same_type(lhs, rhs, |t| t.is_integral() || *t == TypeTag::Bool));
+type_of_binary!(BitwiseOrExpression, |_, lhs, rhs| same_type(lhs, rhs, |t| t.is_integral() || *t == TypeTag::Bool));
+
+type_of_binary!(LogicalAndExpression, |_, lhs, rhs| same_type(lhs, rhs, |t| *t == TypeTag::Bool));
+type_of_binary!(LogicalOrExpression, |_, lhs, rhs| same_type(lhs, rhs, |t| *t == TypeTag::Bool));
+
+impl TypeOf for Conditional {
+    fn type_of(&self, scope: &TypeEnv) -> Result<TypeTag, TypeError> {
+        match self {
+            Conditional::Do { condition, then, otherwise } => {
+                let condition_type = condition.type_of(scope)?;
+                if condition_type != TypeTag::Bool {
+                    return Err(TypeError::new(format!("the condition must be bool, but it is {condition_type}"), condition.span()))
+                }
+
+                let then_type = then.type_of(scope)?;
+                let otherwise_type = otherwise.type_of(scope)?;
+                if then_type != otherwise_type {
+                    return Err(TypeError::new(format!("the branches must have the same type, but found {then_type} and {otherwise_type}"), then.span().or_else(|| otherwise.span())))
+                }
+
+                Ok(then_type)
+            }
+            Conditional::Propagated(inner) => inner.type_of(scope),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::compiler::resolved_type_tag::TypeTag;
+    use crate::compiler::span::Span;
+    use crate::compiler::type_check::{check_program, type_of, TypeEnv, TypeError};
+
+    fn type_of_source(source: &str) -> Result<TypeTag, TypeError> {
+        let expression = Parser::with_lexer(Lexer::from_borrowed(source)).parse_expression().expect("the expression is valid");
+        type_of(&expression, &TypeEnv::new())
+    }
+
+    fn check_source(source: &str) -> Result<(), TypeError> {
+        let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+        check_program(&root, &mut TypeEnv::new())
+    }
+
+    #[test]
+    fn arithmetic_has_the_type_of_its_operands() {
+        assert_eq!(type_of_source("1 + 2"), Ok(TypeTag::I32));
+        assert_eq!(type_of_source("1u8 * 2u8"), Ok(TypeTag::U8));
+    }
+
+    #[test]
+    fn comparison_is_bool() {
+        assert_eq!(type_of_source("1 < 2"), Ok(TypeTag::Bool));
+    }
+
+    #[test]
+    fn ill_typed_operator_is_reported_with_its_span() {
+        assert_eq!(type_of_source("\"a\" - 1"), Err(TypeError {
+            message: "Subtract cannot be applied to string and i32".to_string(),
+            span: Some(Span { start: 0, end: 7 }),
+        }));
+    }
+
+    #[test]
+    fn unsuffixed_integral_literal_takes_the_default_int() {
+        let expression = Parser::with_lexer(Lexer::from_borrowed("1 + 2")).parse_expression().expect("the expression is valid");
+
+        assert_eq!(type_of(&expression, &TypeEnv::new().with_default_int(TypeTag::I64)), Ok(TypeTag::I64));
+    }
+
+    #[test]
+    fn declarations_are_visible_afterwards() {
+        assert_eq!(check_source("var a = 1\nvar b = a + 2\n"), Ok(()));
+    }
+
+    #[test]
+    fn declaration_may_widen_but_not_narrow() {
+        assert_eq!(check_source("var a: i64 = 5i32\n"), Ok(()));
+        assert_eq!(check_source("var a: i8 = 5i32\n"), Err(TypeError {
+            message: "`a` is i8, so i32 cannot be assigned to it".to_string(),
+            span: Some(Span { start: 12, end: 16 }),
+        }));
+    }
+
+    #[test]
+    fn unsuffixed_literal_takes_the_declared_type() {
+        assert_eq!(check_source("var a: i8 = 1\nvar b: f64 = 0.5\n"), Ok(()));
+    }
+
+    #[test]
+    fn condition_must_be_bool() {
+        let error = check_source("if 1 then\nend\n").expect_err("the condition is not bool");
+
+        assert_eq!(error.message, "the condition must be bool, but it is i32");
+        assert_eq!(error.span, Some(Span { start: 3, end: 4 }));
+    }
+}
@@ -0,0 +1,112 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+/// the extension which `compress` appends, and which `decompress` removes.
+const EXTENSION: &str = "gz";
+
+/// gzips `path` into `path.gz`, and returns where it was written.
+/// refuses to overwrite an existing file unless `force` is set.
+pub fn compress_file(path: &Path, force: bool) -> Result<PathBuf> {
+    let mut destination = OsString::from(path.as_os_str());
+    destination.push(".");
+    destination.push(EXTENSION);
+    let destination = PathBuf::from(destination);
+
+    let mut input = BufReader::new(File::open(path).with_context(|| format!("failed to open {}", path.display()))?);
+    let mut encoder = GzEncoder::new(BufWriter::new(create(&destination, force)?), Compression::default());
+    std::io::copy(&mut input, &mut encoder).with_context(|| format!("failed to compress {}", path.display()))?;
+    encoder.finish()?.flush()?;
+
+    Ok(destination)
+}
+
+/// the inverse of [compress_file]: ungzips `path.gz` into `path`, and returns where it was written.
+/// refuses to overwrite an existing file unless `force` is set.
+pub fn decompress_file(path: &Path, force: bool) -> Result<PathBuf> {
+    if path.extension().is_none_or(|extension| extension != EXTENSION) {
+        bail!("{} does not end with .{EXTENSION}", path.display());
+    }
+    let destination = path.with_extension("");
+
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(path).with_context(|| format!("failed to open {}", path.display()))?));
+    let mut output = BufWriter::new(create(&destination, force)?);
+    std::io::copy(&mut decoder, &mut output).with_context(|| format!("failed to decompress {}", path.display()))?;
+    output.flush()?;
+
+    Ok(destination)
+}
+
+fn create(path: &Path, force: bool) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+
+    options.open(path).with_context(|| if path.exists() && !force {
+        format!("{} already exists; pass --force to overwrite it", path.display())
+    } else {
+        format!("failed to create {}", path.display())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::compression::{compress_file, decompress_file};
+
+    /// a fresh directory for a test which writes files.
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join("neosvr-dsl-compression").join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).expect("failed to create the directory");
+        directory
+    }
+
+    #[test]
+    fn compress_then_decompress_gives_the_same_bytes() {
+        let directory = scratch_directory("round_trip");
+        let source = directory.join("sample.lgx");
+        let bytes = (0..1000).map(|i| format!("var a{i} = {i}\n")).collect::<String>().into_bytes();
+        std::fs::write(&source, &bytes).expect("failed to write the sample");
+
+        let compressed = compress_file(&source, false).expect("the sample can be compressed");
+        assert_eq!(compressed, directory.join("sample.lgx.gz"));
+        assert!(std::fs::metadata(&compressed).expect("the compressed file exists").len() < bytes.len() as u64);
+
+        std::fs::remove_file(&source).expect("failed to remove the sample");
+        assert_eq!(decompress_file(&compressed, false).expect("the sample can be decompressed"), source);
+        assert_eq!(std::fs::read(&source).expect("the decompressed file exists"), bytes);
+    }
+
+    #[test]
+    fn compress_refuses_to_overwrite_without_force() {
+        let directory = scratch_directory("overwrite");
+        let source = directory.join("sample.lgx");
+        let compressed = directory.join("sample.lgx.gz");
+        std::fs::write(&source, "var a = 1\n").expect("failed to write the sample");
+        std::fs::write(&compressed, "keep me").expect("failed to write the existing file");
+
+        let error = compress_file(&source, false).expect_err("the destination exists");
+        assert!(format!("{error:#}").contains("already exists; pass --force to overwrite it"), "{error:#}");
+        assert_eq!(std::fs::read_to_string(&compressed).expect("the existing file is kept"), "keep me");
+
+        compress_file(&source, true).expect("--force overwrites the destination");
+        assert_ne!(std::fs::read(&compressed).expect("the file is overwritten"), b"keep me");
+    }
+
+    #[test]
+    fn decompress_requires_the_gz_extension() {
+        let error = decompress_file("sample.lgx".as_ref(), false).expect_err("the extension is not .gz");
+
+        assert_eq!(error.to_string(), "sample.lgx does not end with .gz");
+    }
+}
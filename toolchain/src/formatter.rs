@@ -0,0 +1,126 @@
+use strum::EnumString;
+
+#[derive(EnumString, Eq, PartialEq, Copy, Clone, Debug)]
+#[strum(serialize_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// keeps the line ending which is used the most in the source. ties are broken in favor of LF.
+    Auto,
+}
+
+impl LineEnding {
+    fn resolve(self, source: &str) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Auto => {
+                let crlf = source.matches("\r\n").count();
+                let lf = source.matches('\n').count() - crlf;
+                if crlf > lf {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+/// Formats the source text line by line: trailing whitespaces are removed, the line endings are unified,
+/// and the output ends with exactly one line ending.
+pub fn format(source: &str, line_ending: LineEnding) -> String {
+    let line_ending = line_ending.resolve(source);
+    let mut lines = source
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>();
+
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    if lines.is_empty() {
+        return String::new()
+    }
+
+    let mut formatted = lines.join(line_ending);
+    formatted.push_str(line_ending);
+    formatted
+}
+
+/// a tab advances the column to the next multiple of this.
+const TAB_WIDTH: usize = 4;
+
+/// A line which is wider than the limit passed to [find_long_lines].
+#[derive(Debug, Eq, PartialEq)]
+pub struct LongLine {
+    /// 1-origin
+    pub line: usize,
+    /// counted in columns, with tabs expanded
+    pub width: usize,
+}
+
+pub fn find_long_lines(source: &str, max_line_length: usize) -> Vec<LongLine> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| LongLine {
+            line: index + 1,
+            width: display_width(line),
+        })
+        .filter(|long_line| long_line.width > max_line_length)
+        .collect()
+}
+
+fn display_width(line: &str) -> usize {
+    line.chars().fold(0, |width, c| {
+        if c == '\t' {
+            (width / TAB_WIDTH + 1) * TAB_WIDTH
+        } else {
+            width + 1
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::{find_long_lines, format, LineEnding, LongLine};
+
+    const CRLF_SOURCE: &str = "var a = 1  \r\nvar b = 2\r\n\r\n";
+
+    #[test]
+    fn lf_replaces_every_line_ending() {
+        assert_eq!(format(CRLF_SOURCE, LineEnding::Lf), "var a = 1\nvar b = 2\n");
+    }
+
+    #[test]
+    fn crlf_replaces_every_line_ending() {
+        assert_eq!(format("var a = 1\nvar b = 2", LineEnding::Crlf), "var a = 1\r\nvar b = 2\r\n");
+    }
+
+    #[test]
+    fn auto_keeps_the_line_ending_used_the_most() {
+        assert_eq!(format(CRLF_SOURCE, LineEnding::Auto), "var a = 1\r\nvar b = 2\r\n");
+        assert_eq!(format("var a = 1\r\nvar b = 2\nvar c = 3\n", LineEnding::Auto), "var a = 1\nvar b = 2\nvar c = 3\n");
+    }
+
+    #[test]
+    fn auto_prefers_lf_on_a_tie() {
+        assert_eq!(format("var a = 1\r\nvar b = 2\n", LineEnding::Auto), "var a = 1\nvar b = 2\n");
+    }
+
+    #[test]
+    fn line_as_wide_as_the_limit_is_not_reported() {
+        assert!(find_long_lines("var a = 1\nvar bb = 1\n", 10).is_empty());
+        assert_eq!(find_long_lines("var a = 1\nvar bbb = 1\n", 10), [LongLine { line: 2, width: 11 }]);
+    }
+
+    #[test]
+    fn tab_advances_to_the_next_multiple_of_four() {
+        // `a` takes the column 0, and the tab fills up to the column 4
+        assert_eq!(find_long_lines("a\tb\n", 4), [LongLine { line: 1, width: 5 }]);
+        assert_eq!(find_long_lines("\t\tb\n", 8), [LongLine { line: 1, width: 9 }]);
+        assert!(find_long_lines("abcd\tb\n", 9).is_empty());
+    }
+}
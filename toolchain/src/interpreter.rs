@@ -0,0 +1,1728 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use anyhow::anyhow;
+use log::warn;
+use crate::compiler::lexer::Lexer;
+use crate::compiler::parser::error::ParseError;
+use crate::compiler::parser::{IdentifierOrMemberPath, Parser, RootAst, Statement, UnresolvedTypeName};
+use crate::compiler::parser::expression::{Additive, AdditiveOps, BitwiseAndExpression, BitwiseOrExpression, BitwiseShift, BitwiseShiftOps, BitwiseXorExpression, Cast, Conditional, EqualityCheckExpression, EqualityCheckExpressionOps, Expression, First, LogicalAndExpression, LogicalOrExpression, MatchPattern, Multiplicative, MultiplicativeOps, RelationCheckExpression, RelationCheckExpressionOps, Unary, UnaryOps};
+use crate::compiler::resolved_type_tag::TypeTag;
+
+/// deep enough for any hand-written program, shallow enough not to overflow the stack.
+/// only nesting counts, such as parentheses and casts, so a long chain of `+` does not.
+const DEFAULT_MAX_EVALUATION_DEPTH: usize = 1024;
+
+/// A tree-walking interpreter.
+pub struct Interpreter {
+    scope: HashMap<String, SupportedTypeBox>,
+    /// the names in [Interpreter::scope] which were declared with `const`
+    constants: HashSet<String>,
+    evaluation_depth: usize,
+    max_evaluation_depth: usize,
+    numeric_promotion: bool,
+    warn_implicit_widening: bool,
+    /// the type of an unsuffixed integral literal whose type is not decided by the context
+    default_int: SupportedTypeTag,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum InterpreterError {
+    /// every error which the parser found, in the order of appearance. never empty.
+    SyntaxError(Vec<ParseError>),
+    ExecutionError(anyhow::Error),
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterError::SyntaxError(errors) => {
+                write!(f, "the program has a syntax error: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{e}")?;
+                }
+
+                Ok(())
+            }
+            InterpreterError::ExecutionError(e) => write!(f, "execution failed: {e}"),
+        }
+    }
+}
+
+impl Error for InterpreterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InterpreterError::SyntaxError(errors) => errors.first().map(|e| e as &(dyn Error + 'static)),
+            InterpreterError::ExecutionError(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SupportedTypeTag {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Bool,
+    String,
+    /// the type of the elements is not tracked.
+    List,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SupportedTypeBox {
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    /// every element has the same tag.
+    List(Vec<SupportedTypeBox>),
+}
+
+impl SupportedTypeBox {
+    pub fn tag(&self) -> SupportedTypeTag {
+        match self {
+            SupportedTypeBox::I8(_) => SupportedTypeTag::I8,
+            SupportedTypeBox::U8(_) => SupportedTypeTag::U8,
+            SupportedTypeBox::I16(_) => SupportedTypeTag::I16,
+            SupportedTypeBox::U16(_) => SupportedTypeTag::U16,
+            SupportedTypeBox::I32(_) => SupportedTypeTag::I32,
+            SupportedTypeBox::U32(_) => SupportedTypeTag::U32,
+            SupportedTypeBox::I64(_) => SupportedTypeTag::I64,
+            SupportedTypeBox::U64(_) => SupportedTypeTag::U64,
+            SupportedTypeBox::F32(_) => SupportedTypeTag::F32,
+            SupportedTypeBox::F64(_) => SupportedTypeTag::F64,
+            SupportedTypeBox::Bool(_) => SupportedTypeTag::Bool,
+            SupportedTypeBox::String(_) => SupportedTypeTag::String,
+            SupportedTypeBox::List(_) => SupportedTypeTag::List,
+        }
+    }
+
+    pub fn get_string(&self) -> Option<&str> {
+        self.as_string().ok()
+    }
+
+    pub fn get_i32(&self) -> Option<i32> {
+        self.as_i32().ok()
+    }
+
+    pub fn as_string(&self) -> Result<&str, InterpreterError> {
+        match self {
+            SupportedTypeBox::String(s) => Ok(s),
+            other => Err(other.type_mismatch(SupportedTypeTag::String)),
+        }
+    }
+
+    /// reports that the value does not have the `expected` type, such as "expected u16, found f32".
+    fn type_mismatch(&self, expected: SupportedTypeTag) -> InterpreterError {
+        InterpreterError::ExecutionError(anyhow!(
+            "expected {expected}, found {found}",
+            expected = expected.name(),
+            found = self.tag().name()
+        ))
+    }
+
+    /// every integral value fits in i128 without loss.
+    fn integral_value(&self) -> Option<i128> {
+        match self {
+            SupportedTypeBox::I8(v) => Some((*v).into()),
+            SupportedTypeBox::U8(v) => Some((*v).into()),
+            SupportedTypeBox::I16(v) => Some((*v).into()),
+            SupportedTypeBox::U16(v) => Some((*v).into()),
+            SupportedTypeBox::I32(v) => Some((*v).into()),
+            SupportedTypeBox::U32(v) => Some((*v).into()),
+            SupportedTypeBox::I64(v) => Some((*v).into()),
+            SupportedTypeBox::U64(v) => Some((*v).into()),
+            _ => None,
+        }
+    }
+
+    fn numeric_value_as_f64(&self) -> Option<f64> {
+        match self {
+            SupportedTypeBox::F32(v) => Some((*v).into()),
+            SupportedTypeBox::F64(v) => Some(*v),
+            other => other.integral_value().map(|v| v as f64),
+        }
+    }
+}
+
+/// defines `as_*` accessors, each of which returns the value only if it has the variant's type.
+macro_rules! copied_accessors {
+    ($($accessor:ident: $variant:ident($inner:ty)),* $(,)?) => {
+        impl SupportedTypeBox {
+            $(
+                pub fn $accessor(&self) -> Result<$inner, InterpreterError> {
+                    match self {
+                        SupportedTypeBox::$variant(v) => Ok(*v),
+                        other => Err(other.type_mismatch(SupportedTypeTag::$variant)),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+copied_accessors! {
+    as_i8: I8(i8),
+    as_u8: U8(u8),
+    as_i16: I16(i16),
+    as_u16: U16(u16),
+    as_i32: I32(i32),
+    as_u32: U32(u32),
+    as_i64: I64(i64),
+    as_u64: U64(u64),
+    as_f32: F32(f32),
+    as_f64: F64(f64),
+    as_bool: Bool(bool),
+}
+
+/// writes the value as it would be spelled in the source, without a suffix.
+impl Display for SupportedTypeBox {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupportedTypeBox::I8(v) => write!(f, "{v}"),
+            SupportedTypeBox::U8(v) => write!(f, "{v}"),
+            SupportedTypeBox::I16(v) => write!(f, "{v}"),
+            SupportedTypeBox::U16(v) => write!(f, "{v}"),
+            SupportedTypeBox::I32(v) => write!(f, "{v}"),
+            SupportedTypeBox::U32(v) => write!(f, "{v}"),
+            SupportedTypeBox::I64(v) => write!(f, "{v}"),
+            SupportedTypeBox::U64(v) => write!(f, "{v}"),
+            // Debug always writes a `.` or an exponent, and spells infinity as `inf`
+            SupportedTypeBox::F32(v) if v.is_nan() => write!(f, "nan"),
+            SupportedTypeBox::F32(v) => write!(f, "{v:?}"),
+            SupportedTypeBox::F64(v) if v.is_nan() => write!(f, "nan"),
+            SupportedTypeBox::F64(v) => write!(f, "{v:?}"),
+            SupportedTypeBox::Bool(v) => write!(f, "{v}"),
+            SupportedTypeBox::String(v) => write!(f, "{v:?}"),
+            SupportedTypeBox::List(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Compares two values. Without `promote`, values of different types are never equal.
+/// With `promote`, numeric values are compared by their mathematical value:
+/// integers are compared exactly, and comparisons involving a float are done in f64.
+pub fn values_equal(a: &SupportedTypeBox, b: &SupportedTypeBox, promote: bool) -> bool {
+    if !promote || a.tag() == b.tag() {
+        return a == b
+    }
+
+    if let (Some(a), Some(b)) = (a.integral_value(), b.integral_value()) {
+        return a == b
+    }
+
+    match (a.numeric_value_as_f64(), b.numeric_value_as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// calls a function which is provided by the interpreter itself.
+///
+/// - `len(x)`: the number of chars (not bytes) in the string `x`, as `I64`. `len("héllo")` is `5`.
+///   if `x` is a list, the number of its elements.
+pub fn call_builtin(name: &str, arguments: &[SupportedTypeBox]) -> Result<SupportedTypeBox, InterpreterError> {
+    match (name, arguments) {
+        ("len", [SupportedTypeBox::String(s)]) => {
+            let length = i64::try_from(s.chars().count())
+                .map_err(|e| InterpreterError::ExecutionError(e.into()))?;
+            Ok(SupportedTypeBox::I64(length))
+        }
+        ("len", [SupportedTypeBox::List(elements)]) => {
+            let length = i64::try_from(elements.len())
+                .map_err(|e| InterpreterError::ExecutionError(e.into()))?;
+            Ok(SupportedTypeBox::I64(length))
+        }
+        ("len", [other]) => Err(InterpreterError::ExecutionError(anyhow!(
+            "len is not defined for {tag}",
+            tag = other.tag().name()
+        ))),
+        ("len", _) => Err(InterpreterError::ExecutionError(anyhow!(
+            "len takes 1 argument, but {count} were given",
+            count = arguments.len()
+        ))),
+        _ => Err(InterpreterError::ExecutionError(anyhow!("{name} is not a builtin function"))),
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait CanBeEvaluated {
+    /// children must be evaluated through [Interpreter::evaluate], or [Interpreter::evaluate_nested] if they are nested
+    /// in parentheses, an operand of a prefix operator, or any other construct which the depth limit applies to.
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError>;
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            scope: HashMap::new(),
+            constants: HashSet::new(),
+            evaluation_depth: 0,
+            max_evaluation_depth: DEFAULT_MAX_EVALUATION_DEPTH,
+            numeric_promotion: false,
+            warn_implicit_widening: false,
+            default_int: SupportedTypeTag::I32,
+            warnings: vec![],
+        }
+    }
+
+    pub fn with_max_evaluation_depth(self, max_evaluation_depth: usize) -> Self {
+        Self {
+            max_evaluation_depth,
+            ..self
+        }
+    }
+
+    /// allows operators to mix numeric values of different types.
+    pub fn with_numeric_promotion(self, numeric_promotion: bool) -> Self {
+        Self {
+            numeric_promotion,
+            ..self
+        }
+    }
+
+    /// warns when a declaration implicitly widens its value into the annotated type.
+    pub fn with_warn_implicit_widening(self, warn_implicit_widening: bool) -> Self {
+        Self {
+            warn_implicit_widening,
+            ..self
+        }
+    }
+
+    /// sets the type of unsuffixed integral literals, which is i32 by default. fails if `default_int` is not an integral type.
+    pub fn with_default_int(self, default_int: SupportedTypeTag) -> Result<Self, InterpreterError> {
+        if !default_int.is_integral() {
+            return Err(InterpreterError::ExecutionError(anyhow!("{name} is not an integral type", name = default_int.name())))
+        }
+
+        Ok(Self {
+            default_int,
+            ..self
+        })
+    }
+
+    /// runs the program and returns the values of its declarations and expression statements in source order.
+    pub fn run(source: &str) -> Result<Vec<SupportedTypeBox>, InterpreterError> {
+        let parser = Parser::with_lexer(Lexer::from_borrowed(source));
+        let root = parser.parse::<RootAst>().map_err(InterpreterError::SyntaxError)?;
+
+        Self::new().execute(&root)
+    }
+
+    /// executes the statements, and returns the values of the declarations and expression statements in source order.
+    pub fn execute(&mut self, root: &RootAst) -> Result<Vec<SupportedTypeBox>, InterpreterError> {
+        let mut values = vec![];
+        self.execute_block(root.commands(), &mut values)?;
+
+        Ok(values)
+    }
+
+    /// blocks do not introduce their own scope, so declarations inside of them stay visible after them.
+    fn execute_block(&mut self, statements: &[Statement], values: &mut Vec<SupportedTypeBox>) -> Result<(), InterpreterError> {
+        for statement in statements {
+            match statement {
+                Statement::NodeDeclaration { is_const, identifier, type_tag, rhs, .. } => {
+                    let value = match (type_tag, rhs.as_first()) {
+                        (Some(type_name), Some(First::IntegralLiteral { sequence, suffix: None, .. })) => {
+                            self.declare_literal(identifier.as_str(), sequence, type_name)?
+                        }
+                        (Some(type_name), Some(First::FloatLiteral { sequence, .. })) => {
+                            self.declare_float_literal(identifier.as_str(), sequence, type_name)?
+                        }
+                        (Some(type_name), _) => {
+                            let value = self.evaluate(rhs)?;
+                            self.coerce(identifier.as_str(), value, type_name)?
+                        }
+                        (None, _) => self.evaluate(rhs)?,
+                    };
+                    // redeclaring a name replaces the old variable, along with whether it is a constant
+                    if *is_const {
+                        self.constants.insert(identifier.as_str().to_string());
+                    } else {
+                        self.constants.remove(identifier.as_str());
+                    }
+                    self.scope.insert(identifier.as_str().to_string(), value.clone());
+                    values.push(value);
+                }
+                Statement::Comment { .. } => {}
+                Statement::Assignment { target, rhs } => {
+                    let IdentifierOrMemberPath::Identifier(identifier) = target else {
+                        return Err(InterpreterError::ExecutionError(anyhow!("assigning to a member path ({target}) is not supported")))
+                    };
+                    let name = identifier.as_str();
+                    if self.constants.contains(name) {
+                        return Err(InterpreterError::ExecutionError(anyhow!("`{name}` is declared with `const`, so it cannot be assigned to")))
+                    }
+                    let into = self.scope.get(name)
+                        .map(SupportedTypeBox::tag)
+                        .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("`{name}` is assigned before its declaration")))?;
+                    let value = self.evaluate(rhs)?;
+                    let value = self.coerce_into(name, value, into)?;
+                    self.scope.insert(name.to_string(), value);
+                }
+                Statement::Expression(expression) => {
+                    let value = self.evaluate(expression)?;
+                    values.push(value);
+                }
+                Statement::If { condition, then_block, elseif_branches, else_block } => {
+                    let branches = std::iter::once((condition, then_block))
+                        .chain(elseif_branches.iter().map(|branch| (&branch.condition, &branch.block)));
+
+                    let mut chosen = else_block.as_ref();
+                    for (condition, block) in branches {
+                        if self.evaluate_condition(condition)? {
+                            chosen = Some(block);
+                            break
+                        }
+                    }
+
+                    if let Some(block) = chosen {
+                        self.execute_nested_block(block, values)?;
+                    }
+                }
+                Statement::While { condition, body } => {
+                    while self.evaluate_condition(condition)? {
+                        self.execute_nested_block(body, values)?;
+                    }
+                }
+                Statement::For { variable, from, to, body } => {
+                    self.execute_for(variable.as_str(), from, to, body, values)?;
+                }
+                Statement::NoMoreStatements => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// executes a block which is nested one level deeper, such as the body of a loop.
+    /// it shares the depth limit with [Interpreter::evaluate_nested].
+    fn execute_nested_block(&mut self, statements: &[Statement], values: &mut Vec<SupportedTypeBox>) -> Result<(), InterpreterError> {
+        if self.evaluation_depth >= self.max_evaluation_depth {
+            return Err(InterpreterError::ExecutionError(anyhow!(
+                "the block is nested too deeply (the limit is {max})",
+                max = self.max_evaluation_depth
+            )))
+        }
+
+        self.evaluation_depth += 1;
+        let result = self.execute_block(statements, values);
+        self.evaluation_depth -= 1;
+        result
+    }
+
+    /// the loop variable is only visible inside of the body. after the loop,
+    /// the variable which had the same name before the loop is visible again.
+    fn execute_for(
+        &mut self,
+        variable: &str,
+        from: &Expression,
+        to: &Expression,
+        body: &[Statement],
+        values: &mut Vec<SupportedTypeBox>,
+    ) -> Result<(), InterpreterError> {
+        let from = self.evaluate(from)?;
+        let to = self.evaluate(to)?;
+        let tag = from.tag();
+        let (Some(first), Some(last)) = (from.integral_value(), to.integral_value()) else {
+            return Err(InterpreterError::ExecutionError(anyhow!(
+                "the bounds of a for loop must be integers, but they were {from} and {to}",
+                from = tag.name(),
+                to = to.tag().name()
+            )))
+        };
+        if tag != to.tag() {
+            return Err(InterpreterError::ExecutionError(anyhow!(
+                "the bounds of a for loop must have the same type, but they were {from} and {to}",
+                from = tag.name(),
+                to = to.tag().name()
+            )))
+        }
+
+        let shadowed = self.scope.remove(variable);
+        let shadowed_constant = self.constants.remove(variable);
+        let mut result = Ok(());
+        for current in first..=last {
+            let current = integral_box(tag, current).expect("the value is between the bounds of the same type");
+            self.scope.insert(variable.to_string(), current);
+            result = self.execute_nested_block(body, values);
+            if result.is_err() {
+                break
+            }
+        }
+
+        self.scope.remove(variable);
+        if let Some(shadowed) = shadowed {
+            self.scope.insert(variable.to_string(), shadowed);
+        }
+        if shadowed_constant {
+            self.constants.insert(variable.to_string());
+        }
+
+        result
+    }
+
+    fn evaluate_condition<E: CanBeEvaluated + ?Sized>(&mut self, condition: &E) -> Result<bool, InterpreterError> {
+        self.evaluate(condition)?.as_bool()
+    }
+
+    pub fn evaluate<E: CanBeEvaluated + ?Sized>(&mut self, node: &E) -> Result<SupportedTypeBox, InterpreterError> {
+        node.evaluate(self)
+    }
+
+    /// evaluates a left-associative chain such as `a + b - c` from the left, applying `apply` to each operator.
+    /// this is a loop rather than a recursion into the left operand, so that a long chain does not overflow the stack.
+    fn evaluate_chain<F, O, R>(
+        &mut self,
+        (first, rest): (&F, Vec<(O, &R)>),
+        apply: impl Fn(&Interpreter, O, SupportedTypeBox, SupportedTypeBox) -> Result<SupportedTypeBox, InterpreterError>,
+    ) -> Result<SupportedTypeBox, InterpreterError>
+    where
+        F: CanBeEvaluated + ?Sized,
+        R: CanBeEvaluated + ?Sized,
+    {
+        let mut acc = self.evaluate(first)?;
+        for (operator, rhs) in rest {
+            let rhs = self.evaluate(rhs)?;
+            acc = apply(self, operator, acc, rhs)?;
+        }
+
+        Ok(acc)
+    }
+
+    /// evaluates a node which is nested one level deeper, such as the inside of parentheses.
+    /// fails instead of overflowing the stack once the nesting exceeds the limit.
+    fn evaluate_nested<E: CanBeEvaluated + ?Sized>(&mut self, node: &E) -> Result<SupportedTypeBox, InterpreterError> {
+        if self.evaluation_depth >= self.max_evaluation_depth {
+            return Err(InterpreterError::ExecutionError(anyhow!(
+                "the expression is nested too deeply (the limit is {max})",
+                max = self.max_evaluation_depth
+            )))
+        }
+
+        self.evaluation_depth += 1;
+        let result = node.evaluate(self);
+        self.evaluation_depth -= 1;
+        result
+    }
+
+    /// converts the value of the declaration `name` into the annotated type.
+    /// only the safe casts in the spec are done implicitly.
+    /// an unsuffixed integral literal takes the declared type, so `var x: i8 = -1` does not need a suffix.
+    fn declare_literal(&mut self, name: &str, sequence: &str, type_name: &UnresolvedTypeName) -> Result<SupportedTypeBox, InterpreterError> {
+        let into = Self::resolve_dynamic(type_name)?;
+        if into.is_unsigned() && sequence.starts_with('-') {
+            return Err(InterpreterError::ExecutionError(anyhow!("`{name}` is {into}, which is unsigned, so it cannot hold {sequence}", into = into.name())))
+        }
+
+        match into {
+            // the spec allows integral literals to be floats as well
+            SupportedTypeTag::F32 => Ok(SupportedTypeBox::F32(sequence.parse().expect("an integral literal is a valid float"))),
+            SupportedTypeTag::F64 => Ok(SupportedTypeBox::F64(sequence.parse().expect("an integral literal is a valid float"))),
+            into if into.is_integral() => integral_literal(sequence, into),
+            // such as `var x: bool = 1`, which is reported as the default type being assigned
+            into => {
+                let value = integral_literal(sequence, self.default_int)?;
+                self.coerce_into(name, value, into)
+            }
+        }
+    }
+
+    /// a float literal takes f64 if the declaration says so, as `var x: f64 = 0.1`, instead of being widened from f32.
+    fn declare_float_literal(&mut self, name: &str, sequence: &str, type_name: &UnresolvedTypeName) -> Result<SupportedTypeBox, InterpreterError> {
+        match Self::resolve_dynamic(type_name)? {
+            SupportedTypeTag::F64 => Ok(SupportedTypeBox::F64(sequence.parse().expect("the parser only accepts valid float literals"))),
+            into => {
+                let value = float_literal(sequence)?;
+                self.coerce_into(name, value, into)
+            }
+        }
+    }
+
+    fn coerce(&mut self, name: &str, value: SupportedTypeBox, type_name: &UnresolvedTypeName) -> Result<SupportedTypeBox, InterpreterError> {
+        let into = Self::resolve_dynamic(type_name)?;
+        self.coerce_into(name, value, into)
+    }
+
+    fn coerce_into(&mut self, name: &str, value: SupportedTypeBox, into: SupportedTypeTag) -> Result<SupportedTypeBox, InterpreterError> {
+        let from = value.tag();
+        if from == into {
+            return Ok(value)
+        }
+
+        let widened = widen(value, into)
+            .ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                "`{name}` is {into}, so {from} cannot be assigned to it",
+                into = into.name(),
+                from = from.name(),
+            )))?;
+
+        if self.warn_implicit_widening {
+            let warning = format!("`{name}` is implicitly widened from {from} to {into}", from = from.name(), into = into.name());
+            warn!("{warning}");
+            self.warnings.push(warning);
+        }
+
+        Ok(widened)
+    }
+
+    /// warnings reported while executing, in the order they were found.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<&SupportedTypeBox> {
+        self.scope.get(identifier)
+    }
+
+    /// resolves the type name through [TypeTag], so that every name the compiler knows is recognized,
+    /// even if the interpreter cannot hold a value of that type.
+    fn resolve_dynamic(type_name: &UnresolvedTypeName) -> Result<SupportedTypeTag, InterpreterError> {
+        let type_tag = TypeTag::resolve(type_name)
+            .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("unknown type: {type_name}")))?;
+
+        SupportedTypeTag::from_type_tag(&type_tag)
+            .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("unsupported type: {type_name} cannot be held by the interpreter")))
+    }
+}
+
+impl SupportedTypeTag {
+    /// the name in the source, such as `u16`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SupportedTypeTag::I8 => "i8",
+            SupportedTypeTag::U8 => "u8",
+            SupportedTypeTag::I16 => "i16",
+            SupportedTypeTag::U16 => "u16",
+            SupportedTypeTag::I32 => "i32",
+            SupportedTypeTag::U32 => "u32",
+            SupportedTypeTag::I64 => "i64",
+            SupportedTypeTag::U64 => "u64",
+            SupportedTypeTag::F32 => "f32",
+            SupportedTypeTag::F64 => "f64",
+            SupportedTypeTag::Bool => "bool",
+            SupportedTypeTag::String => "string",
+            SupportedTypeTag::List => "list",
+        }
+    }
+
+    fn is_integral(self) -> bool {
+        matches!(self, Self::I8 | Self::U8 | Self::I16 | Self::U16 | Self::I32 | Self::U32 | Self::I64 | Self::U64)
+    }
+
+    fn is_unsigned(self) -> bool {
+        matches!(self, Self::U8 | Self::U16 | Self::U32 | Self::U64)
+    }
+
+    pub fn from_type_tag(type_tag: &TypeTag) -> Option<Self> {
+        let tag = match type_tag {
+            TypeTag::I8 => SupportedTypeTag::I8,
+            TypeTag::U8 => SupportedTypeTag::U8,
+            TypeTag::I16 => SupportedTypeTag::I16,
+            TypeTag::U16 => SupportedTypeTag::U16,
+            TypeTag::I32 => SupportedTypeTag::I32,
+            TypeTag::U32 => SupportedTypeTag::U32,
+            TypeTag::I64 => SupportedTypeTag::I64,
+            TypeTag::U64 => SupportedTypeTag::U64,
+            TypeTag::F32 => SupportedTypeTag::F32,
+            TypeTag::F64 => SupportedTypeTag::F64,
+            TypeTag::Bool => SupportedTypeTag::Bool,
+            TypeTag::String => SupportedTypeTag::String,
+            _ => return None,
+        };
+
+        Some(tag)
+    }
+}
+
+/// returns `None` if `tag` is not an integral type, or if it cannot represent the value.
+fn integral_box(tag: SupportedTypeTag, value: i128) -> Option<SupportedTypeBox> {
+    let boxed = match tag {
+        SupportedTypeTag::I8 => SupportedTypeBox::I8(value.try_into().ok()?),
+        SupportedTypeTag::U8 => SupportedTypeBox::U8(value.try_into().ok()?),
+        SupportedTypeTag::I16 => SupportedTypeBox::I16(value.try_into().ok()?),
+        SupportedTypeTag::U16 => SupportedTypeBox::U16(value.try_into().ok()?),
+        SupportedTypeTag::I32 => SupportedTypeBox::I32(value.try_into().ok()?),
+        SupportedTypeTag::U32 => SupportedTypeBox::U32(value.try_into().ok()?),
+        SupportedTypeTag::I64 => SupportedTypeBox::I64(value.try_into().ok()?),
+        SupportedTypeTag::U64 => SupportedTypeBox::U64(value.try_into().ok()?),
+        _ => return None,
+    };
+
+    Some(boxed)
+}
+
+/// parses a float literal as f32, which the spec falls back to. `inf` and `nan` are parsed as well, and so are their negations.
+fn float_literal(sequence: &str) -> Result<SupportedTypeBox, InterpreterError> {
+    let value = sequence.parse::<f32>().expect("the parser only accepts valid float literals");
+    if value.is_infinite() && sequence.trim_start_matches('-') != "inf" {
+        return Err(InterpreterError::ExecutionError(anyhow!("`{sequence}` is out of range for f32")))
+    }
+
+    Ok(SupportedTypeBox::F32(value))
+}
+
+/// parses the digits of an integral literal as `tag`, rejecting values which `tag` cannot represent.
+fn integral_literal(sequence: &str, tag: SupportedTypeTag) -> Result<SupportedTypeBox, InterpreterError> {
+    let out_of_range = |_| InterpreterError::ExecutionError(anyhow!("`{sequence}` is out of range for {name}", name = tag.name()));
+
+    let value = match tag {
+        SupportedTypeTag::I8 => SupportedTypeBox::I8(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::U8 => SupportedTypeBox::U8(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::I16 => SupportedTypeBox::I16(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::U16 => SupportedTypeBox::U16(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::I32 => SupportedTypeBox::I32(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::U32 => SupportedTypeBox::U32(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::I64 => SupportedTypeBox::I64(sequence.parse().map_err(out_of_range)?),
+        SupportedTypeTag::U64 => SupportedTypeBox::U64(sequence.parse().map_err(out_of_range)?),
+        other => return Err(InterpreterError::ExecutionError(anyhow!("{other:?} is not an integral type"))),
+    };
+
+    Ok(value)
+}
+
+impl CanBeEvaluated for IdentifierOrMemberPath {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        match self {
+            IdentifierOrMemberPath::Identifier(identifier) => interpreter.get(identifier.as_str())
+                .cloned()
+                .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("{name} is not found", name = identifier.as_str()))),
+            IdentifierOrMemberPath::MemberPath(_) => {
+                Err(InterpreterError::ExecutionError(anyhow!("member access is not supported")))
+            }
+        }
+    }
+}
+
+impl CanBeEvaluated for First {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        match self {
+            First::IntegralLiteral { sequence, suffix, .. } => {
+                // TODO: the spec says the type of an unsuffixed integral literal is decided by its context.
+                //       until then, use the default type, which is i32 as the spec falls back to when the context does not care.
+                let tag = match suffix {
+                    Some(suffix) => TypeTag::from_name(suffix)
+                        .as_ref()
+                        .and_then(SupportedTypeTag::from_type_tag)
+                        .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("unknown suffix: {suffix}")))?,
+                    None => interpreter.default_int,
+                };
+
+                integral_literal(sequence, tag)
+            }
+            // TODO: the spec says the type of a float literal is decided by its context as well.
+            First::FloatLiteral { sequence, .. } => float_literal(sequence),
+            First::StringLiteral { sequence, .. } => Ok(SupportedTypeBox::String(sequence.clone())),
+            First::Variable { identifier } => interpreter.get(identifier.as_str())
+                .cloned()
+                .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("{name} is not found", name = identifier.as_str()))),
+            First::MemberPath { path } => Err(InterpreterError::ExecutionError(anyhow!("{path} cannot be evaluated, because members of nodes are not supported"))),
+            First::Call { callee, args } => {
+                let args = args.iter()
+                    .map(|arg| interpreter.evaluate_nested(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                call_builtin(callee.as_str(), &args)
+            }
+            First::Match { scrutinee, arms } => {
+                let scrutinee = interpreter.evaluate_nested(scrutinee.as_ref())?;
+                for arm in arms {
+                    let matched = match &arm.pattern {
+                        MatchPattern::Literal(literal) => {
+                            let literal = interpreter.evaluate_nested(literal)?;
+                            values_equal(&scrutinee, &literal, interpreter.numeric_promotion)
+                        }
+                        MatchPattern::Wildcard => true,
+                    };
+
+                    if matched {
+                        return interpreter.evaluate_nested(&arm.value)
+                    }
+                }
+
+                Err(InterpreterError::ExecutionError(anyhow!("no arm matched {scrutinee}")))
+            }
+            First::List { elements } => {
+                let elements = elements.iter()
+                    .map(|element| interpreter.evaluate_nested(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(different) = elements.iter().find(|element| element.tag() != elements[0].tag()) {
+                    return Err(InterpreterError::ExecutionError(anyhow!(
+                        "the elements of a list must have the same type, but it has both {first} and {different}",
+                        first = elements[0].tag().name(),
+                        different = different.tag().name()
+                    )))
+                }
+
+                Ok(SupportedTypeBox::List(elements))
+            }
+            First::Index { target, index } => {
+                let target = interpreter.evaluate_nested(target.as_ref())?;
+                let index = interpreter.evaluate_nested(index.as_ref())?;
+                let SupportedTypeBox::List(elements) = target else {
+                    return Err(InterpreterError::ExecutionError(anyhow!("{tag} cannot be indexed", tag = target.tag().name())))
+                };
+                let Some(position) = index.integral_value() else {
+                    return Err(InterpreterError::ExecutionError(anyhow!("the index must be an integer, but it was {tag}", tag = index.tag().name())))
+                };
+
+                // a negative index does not count from the end
+                if position < 0 {
+                    return Err(InterpreterError::ExecutionError(anyhow!("the index must not be negative, but it was {position}")))
+                }
+
+                usize::try_from(position).ok()
+                    .and_then(|position| elements.get(position))
+                    .cloned()
+                    .ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                        "the index {position} is out of the bounds of the list of length {length}",
+                        length = elements.len()
+                    )))
+            }
+            First::True { .. } => Ok(SupportedTypeBox::Bool(true)),
+            First::False { .. } => Ok(SupportedTypeBox::Bool(false)),
+            First::Parenthesized(inner) => interpreter.evaluate_nested(inner.as_ref()),
+        }
+    }
+}
+
+impl CanBeEvaluated for Cast {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        match self {
+            Cast::Do { operand, tp } => {
+                let value = interpreter.evaluate_nested(operand.as_ref())?;
+                let into = Interpreter::resolve_dynamic(tp)?;
+                let from = value.tag();
+                if from == into {
+                    return Ok(value)
+                }
+
+                convert_numeric(&value, into)
+                    .ok_or_else(|| InterpreterError::ExecutionError(anyhow!("{from} cannot be casted to {into}", from = from.name(), into = into.name())))
+            }
+            Cast::Propagated(first) => interpreter.evaluate(first),
+        }
+    }
+}
+
+/// under numeric promotion, converts one operand into the type of the other if that is a safe cast.
+/// otherwise, including signed and unsigned integers of the same width, the operands are left as is.
+fn promote(interpreter: &Interpreter, lhs: SupportedTypeBox, rhs: SupportedTypeBox) -> (SupportedTypeBox, SupportedTypeBox) {
+    let (lhs_tag, rhs_tag) = (lhs.tag(), rhs.tag());
+    if !interpreter.numeric_promotion || lhs_tag == rhs_tag {
+        return (lhs, rhs)
+    }
+
+    if let Some(lhs) = widen(lhs.clone(), rhs_tag) {
+        return (lhs, rhs)
+    }
+
+    if let Some(rhs) = widen(rhs.clone(), lhs_tag) {
+        return (lhs, rhs)
+    }
+
+    (lhs, rhs)
+}
+
+/// performs the "safe cast" described in the spec, i.e. conversions that never lose information.
+fn widen(value: SupportedTypeBox, into: SupportedTypeTag) -> Option<SupportedTypeBox> {
+    use SupportedTypeBox as B;
+    use SupportedTypeTag as T;
+
+    let widened = match (value, into) {
+        (value, into) if value.tag() == into => value,
+        (B::I8(v), T::I16) => B::I16(v.into()),
+        (B::I8(v), T::I32) => B::I32(v.into()),
+        (B::I8(v), T::I64) => B::I64(v.into()),
+        (B::I8(v), T::F32) => B::F32(v.into()),
+        (B::I8(v), T::F64) => B::F64(v.into()),
+        (B::I16(v), T::I32) => B::I32(v.into()),
+        (B::I16(v), T::I64) => B::I64(v.into()),
+        (B::I16(v), T::F32) => B::F32(v.into()),
+        (B::I16(v), T::F64) => B::F64(v.into()),
+        (B::I32(v), T::I64) => B::I64(v.into()),
+        (B::I32(v), T::F64) => B::F64(v.into()),
+        (B::U8(v), T::U16) => B::U16(v.into()),
+        (B::U8(v), T::U32) => B::U32(v.into()),
+        (B::U8(v), T::U64) => B::U64(v.into()),
+        (B::U8(v), T::I16) => B::I16(v.into()),
+        (B::U8(v), T::I32) => B::I32(v.into()),
+        (B::U8(v), T::I64) => B::I64(v.into()),
+        (B::U8(v), T::F32) => B::F32(v.into()),
+        (B::U8(v), T::F64) => B::F64(v.into()),
+        (B::U16(v), T::U32) => B::U32(v.into()),
+        (B::U16(v), T::U64) => B::U64(v.into()),
+        (B::U16(v), T::I32) => B::I32(v.into()),
+        (B::U16(v), T::I64) => B::I64(v.into()),
+        (B::U16(v), T::F32) => B::F32(v.into()),
+        (B::U16(v), T::F64) => B::F64(v.into()),
+        (B::U32(v), T::U64) => B::U64(v.into()),
+        (B::U32(v), T::I64) => B::I64(v.into()),
+        (B::U32(v), T::F64) => B::F64(v.into()),
+        _ => return None,
+    };
+
+    Some(widened)
+}
+
+/// converts the value with the semantics of Rust's `as`:
+/// - integers are truncated to the target width, so `300 as u8` is `44` and `-1 as u32` is `4294967295`.
+/// - floats are rounded toward zero when converted into integers, and saturate at the bounds of the target.
+///   NaN becomes `0`.
+/// - `f64` is rounded to the nearest `f32`.
+fn convert_numeric(value: &SupportedTypeBox, into: SupportedTypeTag) -> Option<SupportedTypeBox> {
+    macro_rules! convert_with_as {
+        ($v:expr) => {
+            match into {
+                SupportedTypeTag::I8 => Some(SupportedTypeBox::I8($v as i8)),
+                SupportedTypeTag::U8 => Some(SupportedTypeBox::U8($v as u8)),
+                SupportedTypeTag::I16 => Some(SupportedTypeBox::I16($v as i16)),
+                SupportedTypeTag::U16 => Some(SupportedTypeBox::U16($v as u16)),
+                SupportedTypeTag::I32 => Some(SupportedTypeBox::I32($v as i32)),
+                SupportedTypeTag::U32 => Some(SupportedTypeBox::U32($v as u32)),
+                SupportedTypeTag::I64 => Some(SupportedTypeBox::I64($v as i64)),
+                SupportedTypeTag::U64 => Some(SupportedTypeBox::U64($v as u64)),
+                SupportedTypeTag::F32 => Some(SupportedTypeBox::F32($v as f32)),
+                SupportedTypeTag::F64 => Some(SupportedTypeBox::F64($v as f64)),
+                SupportedTypeTag::Bool | SupportedTypeTag::String | SupportedTypeTag::List => None,
+            }
+        };
+    }
+
+    match *value {
+        SupportedTypeBox::I8(v) => convert_with_as!(v),
+        SupportedTypeBox::U8(v) => convert_with_as!(v),
+        SupportedTypeBox::I16(v) => convert_with_as!(v),
+        SupportedTypeBox::U16(v) => convert_with_as!(v),
+        SupportedTypeBox::I32(v) => convert_with_as!(v),
+        SupportedTypeBox::U32(v) => convert_with_as!(v),
+        SupportedTypeBox::I64(v) => convert_with_as!(v),
+        SupportedTypeBox::U64(v) => convert_with_as!(v),
+        SupportedTypeBox::F32(v) => convert_with_as!(v),
+        SupportedTypeBox::F64(v) => convert_with_as!(v),
+        SupportedTypeBox::Bool(_) | SupportedTypeBox::String(_) | SupportedTypeBox::List(_) => None,
+    }
+}
+
+impl CanBeEvaluated for Unary {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        use SupportedTypeBox as B;
+
+        match self {
+            Unary::Do { operator, operand } => {
+                let value = interpreter.evaluate_nested(operand.as_ref())?;
+                let result = match (operator, &value) {
+                    (UnaryOps::Negate, B::I8(v)) => Some(B::I8(v.checked_neg().ok_or_else(|| negation_overflowed("i8", v))?)),
+                    (UnaryOps::Negate, B::I16(v)) => Some(B::I16(v.checked_neg().ok_or_else(|| negation_overflowed("i16", v))?)),
+                    (UnaryOps::Negate, B::I32(v)) => Some(B::I32(v.checked_neg().ok_or_else(|| negation_overflowed("i32", v))?)),
+                    (UnaryOps::Negate, B::I64(v)) => Some(B::I64(v.checked_neg().ok_or_else(|| negation_overflowed("i64", v))?)),
+                    (UnaryOps::Negate, B::F32(v)) => Some(B::F32(-v)),
+                    (UnaryOps::Negate, B::F64(v)) => Some(B::F64(-v)),
+                    (UnaryOps::LogicalNot, B::Bool(v)) => Some(B::Bool(!v)),
+                    (UnaryOps::BitwiseNot, B::I8(v)) => Some(B::I8(!v)),
+                    (UnaryOps::BitwiseNot, B::U8(v)) => Some(B::U8(!v)),
+                    (UnaryOps::BitwiseNot, B::I16(v)) => Some(B::I16(!v)),
+                    (UnaryOps::BitwiseNot, B::U16(v)) => Some(B::U16(!v)),
+                    (UnaryOps::BitwiseNot, B::I32(v)) => Some(B::I32(!v)),
+                    (UnaryOps::BitwiseNot, B::U32(v)) => Some(B::U32(!v)),
+                    (UnaryOps::BitwiseNot, B::I64(v)) => Some(B::I64(!v)),
+                    (UnaryOps::BitwiseNot, B::U64(v)) => Some(B::U64(!v)),
+                    _ => None,
+                };
+
+                result.ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                    "{operator} cannot be applied to {tag}",
+                    operator = <&'static str>::from(operator),
+                    tag = value.tag().name()
+                )))
+            }
+            Unary::Propagated(cast) => interpreter.evaluate(cast),
+        }
+    }
+}
+
+fn negation_overflowed(type_name: &str, operand: impl Display) -> InterpreterError {
+    InterpreterError::ExecutionError(anyhow!("{type_name} negation overflowed: -({operand})"))
+}
+
+/// takes the right operand with the accessor of the variant of the left operand, and evaluates `$apply` with both values
+/// and the constructor of the variant. returns `None` if the left operand is none of `$variant`s, or the right one
+/// has another type.
+macro_rules! with_same_tag {
+    ($lhs:expr, $rhs:expr, [$($variant:ident: $accessor:ident),* $(,)?], |$l:ident, $r:ident, $wrap:ident| $apply:expr) => {
+        match $lhs {
+            $(SupportedTypeBox::$variant($l) => $rhs.$accessor().ok().map(|$r| {
+                let ($l, $wrap) = (*$l, SupportedTypeBox::$variant);
+                $apply
+            }),)*
+            _ => None,
+        }
+    };
+}
+
+/// applies the operator if both operands have the same numeric type, and returns `None` otherwise.
+/// integers use the `$checked` method (e.g. `checked_add`) so that an overflow is reported instead of wrapping.
+/// floats use the `$float` operator and follow IEEE 754.
+macro_rules! same_tag_arithmetic {
+    ($lhs:expr, $rhs:expr, $checked:ident, $float:tt, $description:literal) => {
+        with_same_tag!(
+            $lhs, $rhs,
+            [I8: as_i8, U8: as_u8, I16: as_i16, U16: as_u16, I32: as_i32, U32: as_u32, I64: as_i64, U64: as_u64],
+            |l, r, wrap| l.$checked(r).map(wrap).ok_or_else(|| overflowed($lhs.tag().name(), $description, stringify!($float), l, r))
+        ).or_else(|| with_same_tag!($lhs, $rhs, [F32: as_f32, F64: as_f64], |l, r, wrap| Ok(wrap(l $float r))))
+    };
+}
+
+/// reports an integral operation whose result does not fit in its type, such as "i8 addition overflowed: 100 + 100".
+fn overflowed(type_name: &str, description: &str, operator: &str, lhs: impl Display, rhs: impl Display) -> InterpreterError {
+    InterpreterError::ExecutionError(anyhow!("{type_name} {description} overflowed: {lhs} {operator} {rhs}"))
+}
+
+impl CanBeEvaluated for Multiplicative {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        interpreter.evaluate_chain(self.operands(), |interpreter, operator, lhs, rhs| {
+            let (lhs, rhs) = promote(interpreter, lhs, rhs);
+
+            if lhs.tag() == rhs.tag() {
+                // floats follow IEEE 754, so only integers can be divided by zero
+                let divides = matches!(operator, MultiplicativeOps::Divide | MultiplicativeOps::Reminder);
+                if divides && rhs.integral_value() == Some(0) {
+                    return Err(InterpreterError::ExecutionError(anyhow!("division by zero")))
+                }
+
+                let result = match operator {
+                    MultiplicativeOps::Multiply => same_tag_arithmetic!(&lhs, &rhs, checked_mul, *, "multiplication"),
+                    // `-128i8 / -1i8` overflows as well
+                    MultiplicativeOps::Divide => same_tag_arithmetic!(&lhs, &rhs, checked_div, /, "division"),
+                    // truncated, so `-5 % 3` is `-2` as the spec says
+                    MultiplicativeOps::Reminder => same_tag_arithmetic!(&lhs, &rhs, checked_rem, %, "remainder"),
+                }.transpose()?;
+
+                return result.ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                    "multiplicative operation on {tag} is not supported",
+                    tag = lhs.tag().name()
+                )))
+            }
+
+            match (operator, &lhs, &rhs) {
+                // nonstandard: `"ab" * 3` repeats the string
+                (MultiplicativeOps::Multiply, SupportedTypeBox::String(s), SupportedTypeBox::I32(times)) => {
+                    let times = usize::try_from(*times)
+                        .map_err(|e| InterpreterError::ExecutionError(e.into()))?;
+                    Ok(SupportedTypeBox::String(s.repeat(times)))
+                }
+                _ => Err(InterpreterError::ExecutionError(anyhow!(
+                    "multiplicative operation on {lhs} and {rhs} is not supported",
+                    lhs = lhs.tag().name(),
+                    rhs = rhs.tag().name()
+                ))),
+            }
+        })
+    }
+}
+
+impl CanBeEvaluated for Additive {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        interpreter.evaluate_chain(self.operands(), |interpreter, operator, lhs, rhs| {
+            let (lhs, rhs) = promote(interpreter, lhs, rhs);
+
+            let result = match operator {
+                AdditiveOps::Add => same_tag_arithmetic!(&lhs, &rhs, checked_add, +, "addition"),
+                AdditiveOps::Subtract => same_tag_arithmetic!(&lhs, &rhs, checked_sub, -, "subtraction"),
+            }.transpose()?;
+            if let Some(result) = result {
+                return Ok(result)
+            }
+
+            match (operator, &lhs, &rhs) {
+                // other values are not converted into strings implicitly, so `"a" + 1` is an error
+                (AdditiveOps::Add, SupportedTypeBox::String(l), SupportedTypeBox::String(r)) => Ok(SupportedTypeBox::String(format!("{l}{r}"))),
+                _ => Err(InterpreterError::ExecutionError(anyhow!(
+                    "additive operation on {lhs} and {rhs} is not supported",
+                    lhs = lhs.tag().name(),
+                    rhs = rhs.tag().name()
+                ))),
+            }
+        })
+    }
+}
+
+impl CanBeEvaluated for EqualityCheckExpression {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        interpreter.evaluate_chain(self.operands(), |interpreter, operator, lhs, rhs| {
+            let equal = values_equal(&lhs, &rhs, interpreter.numeric_promotion);
+
+            match operator {
+                EqualityCheckExpressionOps::Equal => Ok(SupportedTypeBox::Bool(equal)),
+                EqualityCheckExpressionOps::NotEqual => Ok(SupportedTypeBox::Bool(!equal)),
+            }
+        })
+    }
+}
+
+impl CanBeEvaluated for RelationCheckExpression {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        interpreter.evaluate_chain(self.operands(), |_, operator, lhs, rhs| {
+            let ordering = if lhs.tag() == rhs.tag() {
+                match (lhs.integral_value(), rhs.integral_value()) {
+                    (Some(l), Some(r)) => Some(l.cmp(&r)),
+                    _ => match (lhs.numeric_value_as_f64(), rhs.numeric_value_as_f64()) {
+                        // NaN is unordered, so every relation on it is false
+                        (Some(l), Some(r)) => l.partial_cmp(&r),
+                        _ => return Err(InterpreterError::ExecutionError(anyhow!(
+                            "relational operation on {tag} is not supported",
+                            tag = lhs.tag().name()
+                        ))),
+                    },
+                }
+            } else {
+                return Err(InterpreterError::ExecutionError(anyhow!(
+                    "relational operation on {lhs} and {rhs} is not supported",
+                    lhs = lhs.tag().name(),
+                    rhs = rhs.tag().name()
+                )))
+            };
+
+            let holds = match operator {
+                RelationCheckExpressionOps::Less => ordering.is_some_and(Ordering::is_lt),
+                RelationCheckExpressionOps::LessEqual => ordering.is_some_and(Ordering::is_le),
+                RelationCheckExpressionOps::More => ordering.is_some_and(Ordering::is_gt),
+                RelationCheckExpressionOps::MoreEqual => ordering.is_some_and(Ordering::is_ge),
+                // -1, 0 or 1, as `i32`
+                RelationCheckExpressionOps::Spaceship => {
+                    let ordering = ordering.ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                        "{lhs} and {rhs} are unordered, so `<=>` cannot compare them"
+                    )))?;
+                    return Ok(SupportedTypeBox::I32(ordering as i32))
+                }
+            };
+
+            Ok(SupportedTypeBox::Bool(holds))
+        })
+    }
+}
+
+/// applies the bitwise operator if both operands are integers of the same type, or both are bool.
+macro_rules! same_tag_bitwise {
+    ($lhs:expr, $rhs:expr, $operator:tt) => {
+        with_same_tag!(
+            $lhs, $rhs,
+            [I8: as_i8, U8: as_u8, I16: as_i16, U16: as_u16, I32: as_i32, U32: as_u32, I64: as_i64, U64: as_u64, Bool: as_bool],
+            |l, r, wrap| wrap(l $operator r)
+        )
+    };
+}
+
+macro_rules! bitwise_evaluation {
+    ($name:ident, $operator:tt, $description:literal) => {
+        impl CanBeEvaluated for $name {
+            fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+                interpreter.evaluate_chain(self.operands(), |_, _, lhs, rhs| {
+                    same_tag_bitwise!(&lhs, &rhs, $operator).ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                        concat!($description, " on {lhs} and {rhs} is not supported"),
+                        lhs = lhs.tag().name(),
+                        rhs = rhs.tag().name()
+                    )))
+                })
+            }
+        }
+    };
+}
+
+bitwise_evaluation!(BitwiseAndExpression, &, "bitwise and");
+bitwise_evaluation!(BitwiseXorExpression, ^, "bitwise xor");
+bitwise_evaluation!(BitwiseOrExpression, |, "bitwise or");
+
+/// evaluates `&&` and `||`. the right operand is evaluated only if the left one does not decide the result.
+macro_rules! logical_evaluation {
+    ($name:ident, short_circuit_on: $decisive:literal) => {
+        impl CanBeEvaluated for $name {
+            fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+                let (first, rest) = self.operands();
+                let first = interpreter.evaluate(first)?;
+                if rest.is_empty() {
+                    return Ok(first)
+                }
+
+                // the chain is left-associative, so the first decisive operand decides the whole chain
+                if first.as_bool()? == $decisive {
+                    return Ok(SupportedTypeBox::Bool($decisive))
+                }
+                for (_, operand) in rest {
+                    if interpreter.evaluate(operand)?.as_bool()? == $decisive {
+                        return Ok(SupportedTypeBox::Bool($decisive))
+                    }
+                }
+
+                Ok(SupportedTypeBox::Bool(!$decisive))
+            }
+        }
+    };
+}
+
+logical_evaluation!(LogicalAndExpression, short_circuit_on: false);
+logical_evaluation!(LogicalOrExpression, short_circuit_on: true);
+
+impl CanBeEvaluated for Conditional {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        match self {
+            Conditional::Do { condition, then, otherwise } => {
+                // only the taken branch is evaluated
+                if interpreter.evaluate_condition(condition.as_ref())? {
+                    interpreter.evaluate_nested(then.as_ref())
+                } else {
+                    interpreter.evaluate_nested(otherwise.as_ref())
+                }
+            }
+            Conditional::Propagated(inner) => interpreter.evaluate(inner),
+        }
+    }
+}
+
+/// shifts each integral type with its own width. `$method` is `checked_shl` or `checked_shr`, which returns `None` if `$amount` is not less than the width.
+/// `>>` on a signed type is an arithmetic shift, so it keeps the sign.
+macro_rules! integral_shift {
+    ($lhs:expr, $amount:expr, $method:ident) => {
+        match $lhs {
+            SupportedTypeBox::I8(l) => Some(l.$method($amount).map(SupportedTypeBox::I8)),
+            SupportedTypeBox::U8(l) => Some(l.$method($amount).map(SupportedTypeBox::U8)),
+            SupportedTypeBox::I16(l) => Some(l.$method($amount).map(SupportedTypeBox::I16)),
+            SupportedTypeBox::U16(l) => Some(l.$method($amount).map(SupportedTypeBox::U16)),
+            SupportedTypeBox::I32(l) => Some(l.$method($amount).map(SupportedTypeBox::I32)),
+            SupportedTypeBox::U32(l) => Some(l.$method($amount).map(SupportedTypeBox::U32)),
+            SupportedTypeBox::I64(l) => Some(l.$method($amount).map(SupportedTypeBox::I64)),
+            SupportedTypeBox::U64(l) => Some(l.$method($amount).map(SupportedTypeBox::U64)),
+            _ => None,
+        }
+    };
+}
+
+impl CanBeEvaluated for BitwiseShift {
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<SupportedTypeBox, InterpreterError> {
+        interpreter.evaluate_chain(self.operands(), |_, operator, lhs, rhs| {
+            // the amount can be of any integral type, since it does not take part in the result
+            let amount = rhs.integral_value().ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                "the shift amount must be an integer, but it was {rhs}",
+                rhs = rhs.tag().name()
+            )))?;
+            let too_large = || InterpreterError::ExecutionError(anyhow!(
+                "cannot shift {lhs} by {amount}, which is not less than its width",
+                lhs = lhs.tag().name()
+            ));
+            if amount < 0 {
+                return Err(InterpreterError::ExecutionError(anyhow!("the shift amount must not be negative, but it was {amount}")))
+            }
+            let amount = u32::try_from(amount).map_err(|_| too_large())?;
+
+            let shifted = match operator {
+                BitwiseShiftOps::LeftShift => integral_shift!(&lhs, amount, checked_shl),
+                BitwiseShiftOps::RightShift => integral_shift!(&lhs, amount, checked_shr),
+            };
+
+            shifted
+                .ok_or_else(|| InterpreterError::ExecutionError(anyhow!(
+                    "bit shift on {lhs} is not supported",
+                    lhs = lhs.tag().name()
+                )))?
+                .ok_or_else(too_large)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::parser::expression::{Additive, EqualityCheckExpression, LogicalOrExpression};
+    use crate::compiler::parser::{Parser, RootAst};
+    use crate::interpreter::{call_builtin, values_equal, Interpreter, InterpreterError, SupportedTypeBox, SupportedTypeTag};
+
+    fn evaluate(source: &str) -> Result<SupportedTypeBox, InterpreterError> {
+        let expression = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<LogicalOrExpression>().expect("the expression is valid");
+        Interpreter::new().evaluate(&expression)
+    }
+
+    fn parse(source: &str) -> RootAst {
+        Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid")
+    }
+
+    #[test]
+    fn cast_evaluates_its_operand_once_per_level() {
+        let root = parse("5\n5 as i64\n5 as i64 as u8\n");
+        let values = Interpreter::new().execute(&root).expect("every cast is safe");
+
+        assert_eq!(values, [SupportedTypeBox::I32(5), SupportedTypeBox::I64(5), SupportedTypeBox::U8(5)]);
+    }
+
+    #[test]
+    fn cast_between_unrelated_types_is_rejected() {
+        let error = Interpreter::new().execute(&parse("\"a\" as i32\n")).expect_err("a string is not a number");
+
+        assert_eq!(error.to_string(), "execution failed: string cannot be casted to i32");
+    }
+
+    #[test]
+    fn parenthesized_cast_is_an_operand_of_the_subtraction() {
+        let additive = |source: &str| Parser::with_lexer(Lexer::from_borrowed(source)).parse::<Additive>().expect("the expression is valid");
+
+        assert_eq!(Interpreter::new().evaluate(&additive("(1 as i32) - 2")).expect("both are i32"), SupportedTypeBox::I32(-1));
+        assert_eq!(Interpreter::new().evaluate(&additive("(1 - 2) as i64")).expect("i32 is widened"), SupportedTypeBox::I64(-1));
+    }
+
+    #[test]
+    fn values_of_different_types_are_equal_only_under_promotion() {
+        let (i32_one, i64_one) = (SupportedTypeBox::I32(1), SupportedTypeBox::I64(1));
+
+        assert!(!values_equal(&i32_one, &i64_one, false));
+        assert!(values_equal(&i32_one, &i64_one, true));
+        assert!(!values_equal(&i32_one, &SupportedTypeBox::I64(2), true));
+        assert!(values_equal(&i32_one, &SupportedTypeBox::F64(1.0), true));
+        assert!(!values_equal(&i32_one, &SupportedTypeBox::String("1".to_string()), true));
+    }
+
+    #[test]
+    fn equality_operators_promote_only_under_the_flag() {
+        let equality = |source: &str| Parser::with_lexer(Lexer::from_borrowed(source)).parse::<EqualityCheckExpression>().expect("the expression is valid");
+        let (equal, not_equal) = (equality("1 == 1 as i64"), equality("1 != 1 as i64"));
+
+        let mut strict = Interpreter::new();
+        assert_eq!(strict.evaluate(&equal).expect("different types can be compared"), SupportedTypeBox::Bool(false));
+        assert_eq!(strict.evaluate(&not_equal).expect("different types can be compared"), SupportedTypeBox::Bool(true));
+        let mut promoting = Interpreter::new().with_numeric_promotion(true);
+        assert_eq!(promoting.evaluate(&equal).expect("different types can be compared"), SupportedTypeBox::Bool(true));
+        assert_eq!(promoting.evaluate(&not_equal).expect("different types can be compared"), SupportedTypeBox::Bool(false));
+    }
+
+    #[test]
+    fn multiplicative_operators_on_matching_types() {
+        let root = parse("6 * 7\n10 / 3\n10 % 3\n-10 / 3\n-10 % 3\n1.0 / 0.0\n");
+        let values = Interpreter::new().execute(&root).expect("the operands have the same type");
+
+        assert_eq!(values, [
+            SupportedTypeBox::I32(42),
+            SupportedTypeBox::I32(3),
+            SupportedTypeBox::I32(1),
+            SupportedTypeBox::I32(-3),
+            SupportedTypeBox::I32(-1),
+            SupportedTypeBox::F32(f32::INFINITY),
+        ]);
+    }
+
+    #[test]
+    fn integral_division_by_zero_is_rejected() {
+        for source in ["1 / 0", "1 % 0"] {
+            match evaluate(source) {
+                Err(InterpreterError::ExecutionError(e)) => assert_eq!(e.to_string(), "division by zero", "{source}"),
+                other => panic!("the divisor of {source} is zero, but it was {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn comparison_equality_logical_and_bitwise_operators() {
+        let values = ["1 < 2", "3 == 3", "true && false", "5 & 3", "1 | 2", "5 ^ 3", "2 >= 3"].map(|source| evaluate(source).expect("the operators are well-typed"));
+
+        assert_eq!(values, [
+            SupportedTypeBox::Bool(true),
+            SupportedTypeBox::Bool(true),
+            SupportedTypeBox::Bool(false),
+            SupportedTypeBox::I32(1),
+            SupportedTypeBox::I32(3),
+            SupportedTypeBox::I32(6),
+            SupportedTypeBox::Bool(false),
+        ]);
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        // the right-hand side would fail if it were evaluated
+        assert_eq!(evaluate("false && 1 / 0 == 1").expect("the rhs is skipped"), SupportedTypeBox::Bool(false));
+        assert_eq!(evaluate("true || 1 / 0 == 1").expect("the rhs is skipped"), SupportedTypeBox::Bool(true));
+    }
+
+    #[test]
+    fn relational_operators_require_the_same_type() {
+        match evaluate("1 < 2 as i64") {
+            Err(InterpreterError::ExecutionError(e)) => assert_eq!(e.to_string(), "relational operation on i32 and i64 is not supported"),
+            other => panic!("i32 and i64 are different types, but it was {other:?}"),
+        }
+    }
+
+    #[test]
+    fn len_counts_the_chars_of_a_string() {
+        let len = |argument| call_builtin("len", &[argument]);
+
+        assert_eq!(len(SupportedTypeBox::String("héllo".to_string())).expect("len takes a string"), SupportedTypeBox::I64(5));
+        assert_eq!(len(SupportedTypeBox::String(String::new())).expect("len takes a string"), SupportedTypeBox::I64(0));
+        assert_eq!(len(SupportedTypeBox::I32(1)).expect_err("len does not take an i32").to_string(), "execution failed: len is not defined for i32");
+    }
+
+    #[test]
+    fn unsupported_operation_names_the_types_as_in_the_source() {
+        let cases = [
+            ("1 * 2i64", "multiplicative operation on i32 and i64 is not supported"),
+            ("true * true", "multiplicative operation on bool is not supported"),
+            ("1 & 2u8", "bitwise and on i32 and u8 is not supported"),
+            ("\"a\" - \"b\"", "additive operation on string and string is not supported"),
+        ];
+        for (source, message) in cases {
+            let error = Interpreter::new().execute(&parse(&format!("{source}\n"))).expect_err(source);
+            assert_eq!(error.to_string(), format!("execution failed: {message}"));
+        }
+    }
+
+    #[test]
+    fn if_runs_only_the_first_branch_whose_condition_holds() {
+        let source = |x: i32| format!("var x = {x}\nif x == 1 then\n10\nelseif x >= 1 then\n20\nelseif x >= 1 then\n30\nelse\n40\nend\n");
+        let run = |x| Interpreter::new().execute(&parse(&source(x))).expect("the program is valid");
+
+        assert_eq!(run(1), [SupportedTypeBox::I32(1), SupportedTypeBox::I32(10)]);
+        assert_eq!(run(2), [SupportedTypeBox::I32(2), SupportedTypeBox::I32(20)]);
+        assert_eq!(run(0), [SupportedTypeBox::I32(0), SupportedTypeBox::I32(40)]);
+    }
+
+    #[test]
+    fn if_condition_must_be_bool() {
+        let error = Interpreter::new().execute(&parse("if 1 then\n1\nend\n")).expect_err("1 is not a bool");
+
+        assert_eq!(error.to_string(), "execution failed: expected bool, found i32");
+    }
+
+    #[test]
+    fn while_repeats_the_body_until_the_condition_is_false() {
+        let root = parse("var i = 0\nwhile i < 3\ni = i + 1\ni\nwend\nwhile false\n1 / 0\nwend\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("the program is valid"), [
+            SupportedTypeBox::I32(0),
+            SupportedTypeBox::I32(1),
+            SupportedTypeBox::I32(2),
+            SupportedTypeBox::I32(3),
+        ]);
+    }
+
+    #[test]
+    fn for_runs_over_both_bounds_and_restores_the_shadowed_variable() {
+        let root = parse("var i = 7\nfor i = 1 to 3\ni * 10\nend\nfor i = 3 to 1\ni\nend\ni\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("the program is valid"), [
+            SupportedTypeBox::I32(7),
+            SupportedTypeBox::I32(10),
+            SupportedTypeBox::I32(20),
+            SupportedTypeBox::I32(30),
+            SupportedTypeBox::I32(7),
+        ]);
+    }
+
+    #[test]
+    fn for_bounds_must_be_integers_of_the_same_type() {
+        let error = Interpreter::new().execute(&parse("for i = 1 to 3i64\ni\nend\n")).expect_err("the bounds differ");
+
+        assert_eq!(error.to_string(), "execution failed: the bounds of a for loop must have the same type, but they were i32 and i64");
+
+        let error = Interpreter::new().execute(&parse("for i = 1 to 1.5\ni\nend\n")).expect_err("1.5 is not an integer");
+
+        assert_eq!(error.to_string(), "execution failed: the bounds of a for loop must be integers, but they were i32 and f32");
+    }
+
+    #[test]
+    fn call_is_dispatched_to_the_builtin_of_the_name() {
+        assert_eq!(Interpreter::new().execute(&parse("len(\"héllo\")\n")).expect("len is a builtin"), [SupportedTypeBox::I64(5)]);
+
+        let error = Interpreter::new().execute(&parse("foo(1)\n")).expect_err("foo is not defined");
+        assert_eq!(error.to_string(), "execution failed: foo is not a builtin function");
+        let error = Interpreter::new().execute(&parse("len(\"a\", \"b\")\n")).expect_err("len takes 1 argument");
+        assert_eq!(error.to_string(), "execution failed: len takes 1 argument, but 2 were given");
+    }
+
+    #[test]
+    fn assignment_replaces_the_value_of_a_declared_variable() {
+        assert_eq!(Interpreter::new().execute(&parse("var a = 1\na = a + 1\na\n")).expect("a is declared"), [SupportedTypeBox::I32(1), SupportedTypeBox::I32(2)]);
+
+        let error = Interpreter::new().execute(&parse("b = 1\n")).expect_err("b is not declared");
+        assert_eq!(error.to_string(), "execution failed: `b` is assigned before its declaration");
+        let error = Interpreter::new().execute(&parse("var a = 1\na = 2i64\n")).expect_err("a is i32");
+        assert_eq!(error.to_string(), "execution failed: `a` is i32, so i64 cannot be assigned to it");
+    }
+
+    #[test]
+    fn conditional_evaluates_only_the_chosen_branch() {
+        // the other branch would fail if it were evaluated
+        let root = parse("true ? 1 : 1 / 0\nfalse ? 1 / 0 : 2\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("the other branch is skipped"), [SupportedTypeBox::I32(1), SupportedTypeBox::I32(2)]);
+    }
+
+    #[test]
+    fn integral_overflow_is_an_error_instead_of_wrapping() {
+        let cases = [
+            ("2147483647 + 1", "i32 addition overflowed: 2147483647 + 1"),
+            ("0u8 - 1u8", "u8 subtraction overflowed: 0 - 1"),
+            ("65536 * 65536", "i32 multiplication overflowed: 65536 * 65536"),
+            ("-2147483648 / -1", "i32 division overflowed: -2147483648 / -1"),
+        ];
+        for (source, message) in cases {
+            let error = Interpreter::new().execute(&parse(&format!("{source}\n"))).expect_err(source);
+            assert_eq!(error.to_string(), format!("execution failed: {message}"));
+        }
+    }
+
+    #[test]
+    fn plus_concatenates_strings() {
+        let values = Interpreter::new().execute(&parse("var a = \"ab\"\na + \"c\" + a\n")).expect("both operands are strings");
+
+        assert_eq!(values[1], SupportedTypeBox::String("abcab".to_string()));
+        assert!(Interpreter::new().execute(&parse("\"a\" + 1\n")).is_err());
+    }
+
+    #[test]
+    fn string_times_integer_repeats_the_string() {
+        let values = Interpreter::new().execute(&parse("\"ab\" * 3\n")).expect("a string can be repeated");
+
+        assert_eq!(values, [SupportedTypeBox::String("ababab".to_string())]);
+    }
+
+    #[test]
+    fn spaceship_evaluates_to_the_sign_of_the_comparison_as_i32() {
+        let values = Interpreter::new().execute(&parse("1 <=> 2\n2 <=> 2\n3u8 <=> 2u8\n0.5 <=> 1.5\n")).expect("the operands are comparable");
+
+        assert_eq!(values, [SupportedTypeBox::I32(-1), SupportedTypeBox::I32(0), SupportedTypeBox::I32(1), SupportedTypeBox::I32(-1)]);
+    }
+
+    #[test]
+    fn spaceship_on_nan_shows_the_operands_as_in_the_source() {
+        let error = Interpreter::new().execute(&parse("nan <=> 1.5\n")).expect_err("nan is unordered");
+
+        assert_eq!(error.to_string(), "execution failed: nan and 1.5 are unordered, so `<=>` cannot compare them");
+    }
+
+    #[test]
+    fn const_cannot_be_assigned_to_until_it_is_redeclared_with_var() {
+        let error = Interpreter::new().execute(&parse("const a = 1\na = 2\n")).expect_err("a is a constant");
+        assert_eq!(error.to_string(), "execution failed: `a` is declared with `const`, so it cannot be assigned to");
+
+        let values = Interpreter::new().execute(&parse("const a = 1\nvar a = 2\na = 3\na\n")).expect("a is a variable again");
+        assert_eq!(values.last(), Some(&SupportedTypeBox::I32(3)));
+    }
+
+    #[test]
+    fn list_literal_holds_its_evaluated_elements() {
+        let values = Interpreter::new().execute(&parse("[]\n[1, 2 + 3]\nlen([[1], []])\n")).expect("the lists are valid");
+
+        assert_eq!(values, [
+            SupportedTypeBox::List(vec![]),
+            SupportedTypeBox::List(vec![SupportedTypeBox::I32(1), SupportedTypeBox::I32(5)]),
+            SupportedTypeBox::I64(2),
+        ]);
+    }
+
+    #[test]
+    fn list_of_different_types_is_rejected() {
+        let error = Interpreter::new().execute(&parse("[1, \"a\"]\n")).expect_err("the elements differ");
+
+        assert_eq!(error.to_string(), "execution failed: the elements of a list must have the same type, but it has both i32 and string");
+    }
+
+    #[test]
+    fn index_out_of_the_bounds_of_the_list_is_rejected() {
+        let run = |index: &str| Interpreter::new().execute(&parse(&format!("var a = [10, 20]\na[{index}]\n")));
+
+        assert_eq!(run("1").expect("1 is in the bounds")[1], SupportedTypeBox::I32(20));
+        assert_eq!(run("2").expect_err("2 is out of the bounds").to_string(), "execution failed: the index 2 is out of the bounds of the list of length 2");
+        assert_eq!(run("-1").expect_err("-1 is negative").to_string(), "execution failed: the index must not be negative, but it was -1");
+    }
+
+    #[test]
+    fn only_a_list_can_be_indexed_by_an_integer() {
+        let error = Interpreter::new().execute(&parse("\"ab\"[0]\n")).expect_err("a string is not a list");
+        assert_eq!(error.to_string(), "execution failed: string cannot be indexed");
+
+        let error = Interpreter::new().execute(&parse("[1][true]\n")).expect_err("true is not an integer");
+        assert_eq!(error.to_string(), "execution failed: the index must be an integer, but it was bool");
+    }
+
+    #[test]
+    fn match_without_a_matching_arm_shows_the_scrutinee_as_in_the_source() {
+        let error = Interpreter::new().execute(&parse("match \"c\"\ncase \"a\" => 1\nend\n")).expect_err("no arm matches");
+
+        assert_eq!(error.to_string(), "execution failed: no arm matched \"c\"");
+    }
+
+    #[test]
+    fn unary_operator_on_an_unsupported_type_names_it() {
+        let error = Interpreter::new().execute(&parse("!1u8\n")).expect_err("! is not defined for u8");
+
+        assert_eq!(error.to_string(), "execution failed: LogicalNot cannot be applied to u8");
+    }
+
+    #[test]
+    fn type_the_interpreter_cannot_hold_is_named_as_in_the_source() {
+        let error = Interpreter::new().execute(&parse("var a: IValue<i32> = 1\n")).expect_err("IValue cannot be held");
+
+        assert_eq!(error.to_string(), "execution failed: unsupported type: IValue<i32> cannot be held by the interpreter");
+    }
+
+    #[test]
+    fn mismatched_declaration_names_both_types() {
+        let error = Interpreter::new().execute(&parse("var a: i64 = \"x\"\n")).expect_err("a string is not an i64");
+
+        assert_eq!(error.to_string(), "execution failed: `a` is i64, so string cannot be assigned to it");
+    }
+
+    #[test]
+    fn float_literals_including_inf_and_nan_are_f32_unless_declared() {
+        let values = Interpreter::new().execute(&parse("1.5\n1e3\n-inf\nnan\nvar a: f64 = 0.1\n")).expect("the literals are valid");
+
+        assert_eq!(values[..3], [SupportedTypeBox::F32(1.5), SupportedTypeBox::F32(1000.0), SupportedTypeBox::F32(f32::NEG_INFINITY)]);
+        assert!(matches!(values[3], SupportedTypeBox::F32(nan) if nan.is_nan()));
+        assert_eq!(values[4], SupportedTypeBox::F64(0.1));
+    }
+
+    #[test]
+    fn nesting_deeper_than_the_limit_fails_cleanly() {
+        let root = parse("var a = ((((((1))))))\n");
+        let error = Interpreter::new().with_max_evaluation_depth(4).execute(&root).expect_err("the expression is nested too deeply");
+
+        assert_eq!(error.to_string(), "execution failed: the expression is nested too deeply (the limit is 4)");
+    }
+
+    #[test]
+    fn nesting_within_the_limit_is_evaluated() {
+        let root = parse("var a = -(-(1))\nvar b = len((\"ab\"))\n");
+        let values = Interpreter::new().with_max_evaluation_depth(4).execute(&root).expect("the nesting is within the limit");
+
+        assert_eq!(values, [SupportedTypeBox::I32(1), SupportedTypeBox::I64(2)]);
+    }
+
+    #[test]
+    fn blocks_count_as_nesting() {
+        let root = parse("if true then\nif true then\n1\nend\nend\n");
+
+        assert_eq!(Interpreter::new().with_max_evaluation_depth(2).execute(&root).expect("the blocks are within the limit"), [SupportedTypeBox::I32(1)]);
+        let error = Interpreter::new().with_max_evaluation_depth(1).execute(&root).expect_err("the inner block is nested too deeply");
+        assert_eq!(error.to_string(), "execution failed: the block is nested too deeply (the limit is 1)");
+    }
+
+    #[test]
+    fn long_chain_of_binary_operators_is_evaluated_without_overflowing_the_stack() {
+        let source = format!("var a = {}\n", vec!["1"; 100_000].join(" + "));
+        let values = Interpreter::new().with_max_evaluation_depth(1).execute(&parse(&source)).expect("a chain of `+` is not nested");
+
+        assert_eq!(values, [SupportedTypeBox::I32(100_000)]);
+    }
+
+    #[test]
+    fn casts_count_as_nesting() {
+        let root = parse("var a = 1 as i32 as i32 as i32\n");
+
+        assert!(Interpreter::new().with_max_evaluation_depth(3).execute(&root).is_ok());
+        let error = Interpreter::new().with_max_evaluation_depth(2).execute(&root).expect_err("the casts are nested too deeply");
+        assert_eq!(error.to_string(), "execution failed: the expression is nested too deeply (the limit is 2)");
+    }
+
+    #[test]
+    fn unsuffixed_literal_takes_the_default_int() {
+        let root = parse("var a = 5\n");
+
+        assert_eq!(Interpreter::new().execute(&root).expect("5 is an i32"), [SupportedTypeBox::I32(5)]);
+        let mut interpreter = Interpreter::new().with_default_int(SupportedTypeTag::I64).expect("i64 is integral");
+        assert_eq!(interpreter.execute(&root).expect("5 is an i64"), [SupportedTypeBox::I64(5)]);
+    }
+
+    #[test]
+    fn unsuffixed_literal_out_of_the_default_int_is_rejected() {
+        let mut interpreter = Interpreter::new().with_default_int(SupportedTypeTag::U32).expect("u32 is integral");
+        let error = interpreter.execute(&parse("var a = -1\n")).expect_err("-1 is not a u32");
+
+        assert_eq!(error.to_string(), "execution failed: `-1` is out of range for u32");
+    }
+
+    #[test]
+    fn default_int_must_be_integral() {
+        let error = Interpreter::new().with_default_int(SupportedTypeTag::F32).err().expect("f32 is not integral");
+
+        assert_eq!(error.to_string(), "execution failed: f32 is not an integral type");
+    }
+
+    #[test]
+    fn implicit_widening_warns_only_if_asked() {
+        let root = parse("var x: i64 = 5i32\n");
+
+        let mut interpreter = Interpreter::new().with_warn_implicit_widening(true);
+        assert_eq!(interpreter.execute(&root).expect("i32 widens into i64"), [SupportedTypeBox::I64(5)]);
+        assert_eq!(interpreter.warnings(), ["`x` is implicitly widened from i32 to i64"]);
+
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.execute(&root).expect("i32 widens into i64"), [SupportedTypeBox::I64(5)]);
+        assert!(interpreter.warnings().is_empty());
+    }
+
+    #[test]
+    fn bitwise_operators_keep_the_width_and_the_sign() {
+        let root = parse("0xF0 & 0x0F\n0xF0 | 0x0Fu8 as i32\n1 << 4\n-8 >> 1\n0x80u8 >> 7\n");
+        let values = Interpreter::new().execute(&root).expect("the operators are well-typed");
+
+        assert_eq!(values, [
+            SupportedTypeBox::I32(0),
+            SupportedTypeBox::I32(0xFF),
+            SupportedTypeBox::I32(16),
+            SupportedTypeBox::I32(-4),
+            SupportedTypeBox::U8(1),
+        ]);
+    }
+
+    #[test]
+    fn shift_by_the_width_or_more_is_rejected() {
+        let error = Interpreter::new().execute(&parse("1u8 << 8\n")).expect_err("u8 is 8 bits wide");
+
+        assert_eq!(error.to_string(), "execution failed: cannot shift u8 by 8, which is not less than its width");
+    }
+
+    #[test]
+    fn accessor_returns_the_value_of_its_variant() {
+        assert_eq!(SupportedTypeBox::U16(7).as_u16().expect("the value is u16"), 7);
+        assert_eq!(SupportedTypeBox::F64(1.5).as_f64().expect("the value is f64"), 1.5);
+        assert!(SupportedTypeBox::Bool(true).as_bool().expect("the value is bool"));
+    }
+
+    #[test]
+    fn accessor_names_both_types_on_another_variant() {
+        let error = SupportedTypeBox::F32(1.0).as_u16().expect_err("the value is f32");
+        assert_eq!(error.to_string(), "execution failed: expected u16, found f32");
+
+        let error = SupportedTypeBox::I64(1).as_i32().expect_err("the value is i64");
+        assert_eq!(error.to_string(), "execution failed: expected i32, found i64");
+    }
+}
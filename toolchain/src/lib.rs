@@ -0,0 +1,7 @@
+//! The toolchain of the DSL. The `neosvr-dsl` binary is a command line front end of this crate.
+pub mod compiler;
+pub mod compression;
+pub mod formatter;
+pub mod interpreter;
+pub mod stub;
+pub mod test_interpreter;
@@ -1,14 +1,32 @@
-mod compiler;
-
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::bail;
 use clap::Parser;
 use clap::Subcommand;
 use fern::colors::ColoredLevelConfig;
-use log::{LevelFilter, trace};
+use log::{error, LevelFilter, trace, warn};
 use strum::EnumString;
+use neosvr_dsl::compiler::ast_dump::{self, AstFormat};
+use neosvr_dsl::compiler::{decompile, fold};
+use neosvr_dsl::compiler::diagnostic::Diagnostic;
+use neosvr_dsl::compiler::graph::NodeGraph;
+use neosvr_dsl::compiler::lexer::Lexer;
+use neosvr_dsl::compiler::output::CompiledProgram;
+use neosvr_dsl::compiler::parser::{Parser as SourceParser, RootAst};
+use neosvr_dsl::compiler::resolved_type_tag::TypeTag;
+use neosvr_dsl::compiler::span::LineIndex;
+use neosvr_dsl::compiler::stats::ProgramStats;
+use neosvr_dsl::compiler::symbols::SymbolTable;
+use neosvr_dsl::compiler::token_dump;
+use neosvr_dsl::compiler::type_check::{self, TypeEnv};
+use neosvr_dsl::{compression, formatter, stub};
+use neosvr_dsl::formatter::LineEnding;
+use neosvr_dsl::interpreter::{Interpreter, SupportedTypeTag};
 
 #[derive(Parser)]
+#[clap(after_help = "EXIT STATUS:\n    0    succeeded\n    1    the input has errors, such as a syntax error\n    2    the arguments are invalid, or a file could not be read or written")]
 struct ToolChainArgs {
     #[clap(long)]
     log_level: ToolChainLogLevel,
@@ -16,29 +34,101 @@ struct ToolChainArgs {
     color_policy: ColorPolicy,
     #[clap(long)]
     log_file: Option<PathBuf>,
+    /// stops after reporting this many errors in the source. unlimited if omitted
+    #[clap(long)]
+    max_errors: Option<usize>,
+    /// `json` prints each error in the input as a JSON object per line on stderr, instead of logging it
+    #[clap(long, default_value = "human")]
+    error_format: ErrorFormat,
     #[clap(subcommand)]
     sub_command: ToolChainSubCommand,
 }
 
 #[derive(Subcommand)]
 enum ToolChainSubCommand {
+    /// gzips the file into the same path with `.gz` appended.
     Compress {
         path: PathBuf,
+        /// overwrites the destination if it exists
+        #[clap(long)]
+        force: bool,
     },
+    /// ungzips a `.gz` file into the same path without `.gz`.
     Decompress {
         path: PathBuf,
+        /// overwrites the destination if it exists
+        #[clap(long)]
+        force: bool,
     },
+    /// writes the node graph of the program as JSON.
     Compile {
         source_file: PathBuf,
+        /// writes to stdout if omitted
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+        /// stops at the given stage, and writes its result instead of the compiled program.
+        #[clap(long, default_value = "graph")]
+        emit: EmitStage,
+        /// the type of unsuffixed integral literals in the node graph
+        #[clap(long, default_value = "i32", parse(try_from_str = parse_integral_type))]
+        default_int: TypeTag,
+        /// replaces expressions which only consist of literals and operators with their values before compiling
+        #[clap(long)]
+        fold_constants: bool,
     },
+    /// executes the source file with the interpreter, and prints the value of each declaration and expression statement in the order of execution.
+    Run {
+        source_file: PathBuf,
+        /// the type of unsuffixed integral literals
+        #[clap(long, default_value = "i32", parse(try_from_str = parse_integral_type))]
+        default_int: TypeTag,
+        /// allows operators to mix numeric values of different types, such as `1i32 + 2i64`
+        #[clap(long)]
+        numeric_promotion: bool,
+        /// warns when a declaration implicitly widens its value into the annotated type
+        #[clap(long)]
+        warn_implicit_widening: bool,
+    },
+    /// prints a stub for the node described by the JSON file.
     GenerateStub {
         json_file: PathBuf,
     },
     DumpJson {
         json_file: PathBuf,
     },
+    /// prints the declarations which the node graph, such as what `compile --emit graph` writes, is made from.
+    Decompile {
+        json_file: PathBuf,
+    },
+    /// prints each token with its span, one per line.
+    DumpTokens {
+        source_file: PathBuf,
+        /// prints a JSON array instead
+        #[clap(long)]
+        json: bool,
+    },
+    /// prints the parsed tree.
     DumpAst {
         source_file: PathBuf,
+        #[clap(long, default_value = "debug")]
+        format: AstFormat,
+    },
+    /// prints counts of statements, declarations, identifiers and operators.
+    Stats {
+        source_file: PathBuf,
+    },
+    /// prints every declared symbol with its resolved type and position.
+    DumpSymbols {
+        source_file: PathBuf,
+    },
+    /// rewrites the source file in place.
+    Format {
+        source_file: PathBuf,
+        #[clap(long, default_value = "auto")]
+        line_ending: LineEnding,
+        /// warns about every line wider than this after formatting. a tab is up to 4 columns wide.
+        #[clap(long)]
+        max_line_length: Option<usize>,
     },
 }
 
@@ -51,6 +141,7 @@ enum ColorPolicy {
 }
 
 impl ColorPolicy {
+    /// `stream` is where the colored output goes. each output should ask for its own stream.
     fn determine(self, stream: atty::Stream) -> bool {
         match self {
             ColorPolicy::Always => true,
@@ -60,6 +151,27 @@ impl ColorPolicy {
     }
 }
 
+#[derive(EnumString, Eq, PartialEq, Copy, Clone)]
+#[strum(serialize_all = "camelCase")]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// what the `compile` subcommand writes out.
+#[derive(EnumString, Eq, PartialEq, Copy, Clone)]
+#[strum(serialize_all = "camelCase")]
+enum EmitStage {
+    /// each token on its own line.
+    Tokens,
+    /// the parsed tree, in the same format as `dump-ast`.
+    Ast,
+    /// the declarations of the program.
+    Json,
+    /// the node graph in JSON, which Resonite can import. only literals and arithmetic can be compiled so far.
+    Graph,
+}
+
 #[derive(EnumString, Eq, PartialEq, Copy, Clone)]
 #[strum(serialize_all = "camelCase")]
 enum ToolChainLogLevel {
@@ -99,6 +211,9 @@ fn setup_logger(log_level: ToolChainLogLevel, do_color: bool, output_file: Optio
         .chain(std::io::stderr());
 
     if let Some(log_file) = output_file {
+        if let Some(directory) = log_file.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
         x = x.chain(fern::log_file(log_file)?);
     }
 
@@ -106,11 +221,270 @@ fn setup_logger(log_level: ToolChainLogLevel, do_color: bool, output_file: Optio
     Ok(())
 }
 
+/// the input has errors, such as a syntax error.
+const EXIT_INPUT_ERROR: i32 = 1;
+/// the arguments are invalid, or a file could not be read or written. clap uses this for invalid arguments too.
+const EXIT_USAGE_ERROR: i32 = 2;
+
+/// a source path which means the standard input
+const STDIN_PATH: &str = "-";
+
+fn parse_integral_type(name: &str) -> anyhow::Result<TypeTag> {
+    let type_tag: TypeTag = name.parse()?;
+    if !type_tag.is_integral() {
+        bail!("{type_tag} is not an integral type");
+    }
+
+    Ok(type_tag)
+}
+
+/// reads the source into a lexer which owns it, so that it is held only once, however large it is.
+fn read_lexer(path: &Path) -> std::io::Result<Lexer<'static>> {
+    if path == Path::new(STDIN_PATH) {
+        Lexer::from_reader(std::io::stdin().lock())
+    } else {
+        Lexer::from_reader(File::open(path)?)
+    }
+}
+
+fn read_source(path: &Path) -> std::io::Result<String> {
+    if path == Path::new(STDIN_PATH) {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// reports errors in the input in the format chosen by `--error-format`.
+#[derive(Copy, Clone)]
+struct Reporter<'a> {
+    error_format: ErrorFormat,
+    /// the input, which JSON diagnostics refer to
+    file: &'a Path,
+}
+
+impl Reporter<'_> {
+    /// reports every diagnostic and exits. `source` is the content of the input, which locates the diagnostics.
+    /// `what` is what has failed, such as "parse".
+    fn exit(self, what: &str, source: &str, diagnostics: &[Diagnostic]) -> ! {
+        let line_index = LineIndex::new(source);
+        for diagnostic in diagnostics {
+            match self.error_format {
+                ErrorFormat::Human => error!("failed to {what}: {human}", human = diagnostic.to_human(&line_index)),
+                ErrorFormat::Json => eprintln!("{json}", json = diagnostic.to_json(self.file, source)),
+            }
+        }
+
+        std::process::exit(EXIT_INPUT_ERROR);
+    }
+}
+
+/// parses the whole source. if it fails, reports every error (up to `max_errors`) and exits.
+fn parse_or_exit(lexer: Lexer, max_errors: Option<usize>, reporter: Reporter) -> RootAst {
+    let parser = SourceParser::with_lexer(lexer);
+    let parser = match max_errors {
+        Some(max_errors) => parser.with_max_errors(max_errors),
+        None => parser,
+    };
+
+    match parser.parse::<RootAst>() {
+        Ok(root) => root,
+        Err(errors) => {
+            let diagnostics = errors.iter().map(Diagnostic::from).collect::<Vec<_>>();
+            reporter.exit("parse", parser.source(), &diagnostics)
+        }
+    }
+}
+
+/// reports the I/O error and exits.
+fn or_exit<T>(result: std::io::Result<T>, what: &str) -> T {
+    result.unwrap_or_else(|e| {
+        error!("failed to {what}: {e}");
+        std::process::exit(EXIT_USAGE_ERROR);
+    })
+}
+
+/// the parser and the interpreter recurse for each level of nesting, and a debug build takes tens of kilobytes per level.
+/// this is enough for the default limit of both, which the stack of the main thread is not.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
 fn main() {
+    let toolchain = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_toolchain)
+        .expect("failed to spawn the thread");
+
+    if let Err(panic) = toolchain.join() {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+fn run_toolchain() {
     let args: ToolChainArgs = ToolChainArgs::parse();
-    setup_logger(args.log_level, args.color_policy.determine(atty::Stream::Stdout), args.log_file)
-        .unwrap_or_default();
+    // the logger writes to stderr, so whether stdout is a terminal does not matter
+    if let Err(e) = setup_logger(args.log_level, args.color_policy.determine(atty::Stream::Stderr), args.log_file) {
+        // the logger is what failed, so this is the only way to tell
+        eprintln!("failed to set up the logger: {e}");
+        std::process::exit(EXIT_USAGE_ERROR);
+    }
     trace!("Hello!");
+    let error_format = args.error_format;
+
+    match args.sub_command {
+        ToolChainSubCommand::Compile { source_file, output, emit, default_int, fold_constants } => {
+            let owner = or_exit(read_lexer(&source_file), "read the source file");
+            let source = owner.source();
+            let reporter = Reporter { error_format, file: &source_file };
+            let lexer = Lexer::from_borrowed(source);
+            let parse = |lexer| parse_or_exit(lexer, args.max_errors, reporter);
+            let fold = |root| {
+                if !fold_constants {
+                    return root
+                }
+
+                let default_int = SupportedTypeTag::from_type_tag(&default_int).expect("an integral type is supported");
+                fold::fold_program(root, default_int).unwrap_or_else(|e| {
+                    reporter.exit("fold constants", source, &[Diagnostic::error(e.to_string())])
+                })
+            };
+            // before folding, so that an ill-typed operator is reported with where it is
+            let check = |root: &RootAst| {
+                let mut scope = TypeEnv::new().with_default_int(default_int.clone());
+                if let Err(e) = type_check::check_program(root, &mut scope) {
+                    reporter.exit("type check", source, &[Diagnostic::from(&e)])
+                }
+            };
+            let emitted = match emit {
+                EmitStage::Tokens => match token_dump::dump(&lexer) {
+                    Ok(tokens) => tokens,
+                    Err(e) => reporter.exit("lex", source, &[Diagnostic::from(&e)]),
+                },
+                EmitStage::Ast => ast_dump::dump(&fold(parse(lexer)), AstFormat::Debug),
+                EmitStage::Json => {
+                    let root = parse(lexer);
+                    check(&root);
+                    let root = fold(root);
+                    let compiled = match CompiledProgram::compile(&root, &default_int) {
+                        Ok(compiled) => compiled,
+                        Err(e) => reporter.exit("compile", source, &[Diagnostic::error(e.to_string())]),
+                    };
+
+                    serde_json::to_string_pretty(&compiled).expect("failed to serialize") + "\n"
+                }
+                EmitStage::Graph => {
+                    let root = parse(lexer);
+                    check(&root);
+                    let root = fold(root);
+                    let graph = match NodeGraph::compile(&root, &default_int) {
+                        Ok(graph) => graph,
+                        Err(e) => reporter.exit("compile", source, &[Diagnostic::error(e.to_string())]),
+                    };
+
+                    serde_json::to_string_pretty(&graph).expect("failed to serialize") + "\n"
+                }
+            };
+
+            match output {
+                Some(output) => or_exit(std::fs::write(output, emitted), "write the output"),
+                None => print!("{emitted}"),
+            }
+        }
+        ToolChainSubCommand::DumpTokens { source_file, json } => {
+            let source = or_exit(read_source(&source_file), "read the source file");
+            let reporter = Reporter { error_format, file: &source_file };
+            let lexer = Lexer::from_borrowed(&source);
+            let dumped = if json {
+                token_dump::dump_json(&lexer)
+            } else {
+                token_dump::dump(&lexer)
+            };
+            match dumped {
+                Ok(dumped) => print!("{dumped}"),
+                Err(e) => reporter.exit("lex", &source, &[Diagnostic::from(&e)]),
+            }
+        }
+        ToolChainSubCommand::DumpAst { source_file, format } => {
+            let source = or_exit(read_source(&source_file), "read the source file");
+            let root = parse_or_exit(Lexer::from_borrowed(&source), args.max_errors, Reporter { error_format, file: &source_file });
+            print!("{}", ast_dump::dump(&root, format));
+        }
+        ToolChainSubCommand::Run { source_file, default_int, numeric_promotion, warn_implicit_widening } => {
+            let source = or_exit(read_source(&source_file), "read the source file");
+            let reporter = Reporter { error_format, file: &source_file };
+            let root = parse_or_exit(Lexer::from_borrowed(&source), args.max_errors, reporter);
+            let default_int = SupportedTypeTag::from_type_tag(&default_int).expect("an integral type is supported");
+            let mut interpreter = Interpreter::new()
+                .with_numeric_promotion(numeric_promotion)
+                .with_warn_implicit_widening(warn_implicit_widening)
+                .with_default_int(default_int)
+                .unwrap_or_else(|e| reporter.exit("run", &source, &[Diagnostic::error(e.to_string())]));
+            match interpreter.execute(&root) {
+                Ok(values) => {
+                    for value in values {
+                        println!("{value}");
+                    }
+                }
+                Err(e) => reporter.exit("run", &source, &[Diagnostic::error(e.to_string())]),
+            }
+        }
+        ToolChainSubCommand::GenerateStub { json_file } => {
+            let json = or_exit(read_source(&json_file), "read the JSON file");
+            match stub::generate(&json) {
+                Ok(stub) => print!("{stub}"),
+                Err(e) => Reporter { error_format, file: &json_file }.exit("generate the stub", &json, &[Diagnostic::error(format!("{e:#}"))]),
+            }
+        }
+        ToolChainSubCommand::Decompile { json_file } => {
+            let json = or_exit(read_source(&json_file), "read the JSON file");
+            match decompile::decompile(&json) {
+                Ok(source) => print!("{source}"),
+                Err(e) => Reporter { error_format, file: &json_file }.exit("decompile", &json, &[Diagnostic::error(format!("{e:#}"))]),
+            }
+        }
+        ToolChainSubCommand::Stats { source_file } => {
+            let lexer = or_exit(read_lexer(&source_file), "read the source file");
+            let root = parse_or_exit(lexer, args.max_errors, Reporter { error_format, file: &source_file });
+            print!("{}", ProgramStats::collect(&root));
+        }
+        ToolChainSubCommand::DumpSymbols { source_file } => {
+            let source = or_exit(read_source(&source_file), "read the source file");
+            let root = parse_or_exit(Lexer::from_borrowed(&source), args.max_errors, Reporter { error_format, file: &source_file });
+            print!("{}", SymbolTable::collect(&source, &root));
+        }
+        ToolChainSubCommand::Format { source_file, line_ending, max_line_length } => {
+            let source = or_exit(std::fs::read_to_string(&source_file), "read the source file");
+            let formatted = formatter::format(&source, line_ending);
+            if let Some(max_line_length) = max_line_length {
+                for long_line in formatter::find_long_lines(&formatted, max_line_length) {
+                    warn!(
+                        "{path}:{line}: the line is {width} columns wide, which exceeds {max_line_length}",
+                        path = source_file.display(),
+                        line = long_line.line,
+                        width = long_line.width,
+                    );
+                }
+            }
+            or_exit(std::fs::write(&source_file, formatted), "write the formatted source");
+        }
+        ToolChainSubCommand::Compress { path, force } => {
+            if let Err(e) = compression::compress_file(&path, force) {
+                error!("failed to compress: {e:#}");
+                std::process::exit(EXIT_USAGE_ERROR);
+            }
+        }
+        ToolChainSubCommand::Decompress { path, force } => {
+            if let Err(e) = compression::decompress_file(&path, force) {
+                error!("failed to decompress: {e:#}");
+                std::process::exit(EXIT_USAGE_ERROR);
+            }
+        }
+        ToolChainSubCommand::DumpJson { .. } => {
+            error!("this subcommand is not implemented yet");
+            std::process::exit(EXIT_USAGE_ERROR);
+        }
+    }
 
     trace!("Bye!");
 }
@@ -0,0 +1,135 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use crate::compiler::lexer::KEYWORDS;
+use crate::compiler::resolved_type_tag::TypeTag;
+
+/// The input of the `generate-stub` subcommand. e.g.:
+///
+/// ```json
+/// {
+///   "name": "ValueAdd",
+///   "inputs": [{ "name": "a", "type": "i32" }, { "name": "b", "type": "i32" }],
+///   "outputs": [{ "name": "result", "type": "i32" }]
+/// }
+/// ```
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NodeDescription {
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<Port>,
+    #[serde(default)]
+    pub outputs: Vec<Port>,
+}
+
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Port {
+    pub name: String,
+    /// a type name which can be written in the source, such as `i32` or `Slot`.
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+impl NodeDescription {
+    /// reports the first offending field by its path, e.g. `inputs[1].type`.
+    /// a keyword is rejected as a name, because the stub could not be parsed.
+    fn validate(&self) -> Result<()> {
+        if !is_identifier(&self.name) || KEYWORDS.contains(&self.name.as_str()) {
+            bail!("name: `{name}` is not an identifier", name = self.name);
+        }
+
+        for (kind, ports) in [("inputs", &self.inputs), ("outputs", &self.outputs)] {
+            for (index, port) in ports.iter().enumerate() {
+                if !is_identifier(&port.name) || KEYWORDS.contains(&port.name.as_str()) {
+                    bail!("{kind}[{index}].name: `{name}` is not an identifier", name = port.name);
+                }
+
+                if port.type_name.parse::<TypeTag>().is_err() {
+                    bail!("{kind}[{index}].type: `{type_name}` is not a known type", type_name = port.type_name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Generates a stub from the JSON node description: the inputs are listed in comments,
+/// and each output becomes a declaration which refers to it.
+pub fn generate(json: &str) -> Result<String> {
+    let node: NodeDescription = serde_json::from_str(json).context("malformed node description")?;
+    node.validate()?;
+
+    let mut stub = format!("// {name}\n", name = node.name);
+    if !node.inputs.is_empty() {
+        stub.push_str("// inputs:\n");
+        for input in &node.inputs {
+            stub.push_str(&format!("//   {name}: {type_name}\n", name = input.name, type_name = input.type_name));
+        }
+    }
+
+    for output in &node.outputs {
+        stub.push_str(&format!(
+            "var {name}: {type_name} = {node}.{name}\n",
+            name = output.name,
+            type_name = output.type_name,
+            node = node.name
+        ));
+    }
+
+    Ok(stub)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stub::generate;
+
+    #[test]
+    fn inputs_are_listed_and_outputs_are_declared() {
+        let json = r#"{
+            "name": "ValueAdd",
+            "inputs": [{ "name": "a", "type": "i32" }, { "name": "b", "type": "i32" }],
+            "outputs": [{ "name": "result", "type": "i32" }]
+        }"#;
+
+        assert_eq!(generate(json).expect("the description is valid"), "\
+// ValueAdd
+// inputs:
+//   a: i32
+//   b: i32
+var result: i32 = ValueAdd.result
+");
+    }
+
+    #[test]
+    fn offending_field_is_reported_by_its_path() {
+        let json = r#"{ "name": "N", "inputs": [{ "name": "a", "type": "i32" }, { "name": "b", "type": "nope" }] }"#;
+        assert_eq!(generate(json).expect_err("nope is unknown").to_string(), "inputs[1].type: `nope` is not a known type");
+
+        let json = r#"{ "name": "1N" }"#;
+        assert_eq!(generate(json).expect_err("1N is not an identifier").to_string(), "name: `1N` is not an identifier");
+    }
+
+    #[test]
+    fn keyword_is_not_an_identifier() {
+        let json = r#"{ "name": "var" }"#;
+        assert_eq!(generate(json).expect_err("var is a keyword").to_string(), "name: `var` is not an identifier");
+
+        let json = r#"{ "name": "N", "outputs": [{ "name": "if", "type": "i32" }] }"#;
+        assert_eq!(generate(json).expect_err("if is a keyword").to_string(), "outputs[0].name: `if` is not an identifier");
+    }
+
+    #[test]
+    fn generic_type_is_accepted() {
+        let json = r#"{ "name": "N", "outputs": [{ "name": "value", "type": "IValue<i32>" }] }"#;
+
+        assert_eq!(generate(json).expect("IValue<i32> is a known type"), "// N\nvar value: IValue<i32> = N.value\n");
+    }
+}
@@ -0,0 +1,26 @@
+use crate::compiler::lexer::Lexer;
+use crate::compiler::parser::{Parser, RootAst};
+use crate::interpreter::{Interpreter, InterpreterError, SupportedTypeBox};
+
+/// A thin wrapper of [Interpreter] which keeps the scope around, so that the result can be inspected by name.
+pub struct TestInterpreter {
+    interpreter: Interpreter,
+}
+
+impl TestInterpreter {
+    /// if the source cannot be parsed, [InterpreterError::SyntaxError] holds what the parser reported.
+    pub fn create_and_execute(source: &str) -> Result<Self, InterpreterError> {
+        let parser = Parser::with_lexer(Lexer::from_borrowed(source));
+        let root = parser.parse::<RootAst>().map_err(InterpreterError::SyntaxError)?;
+        let mut interpreter = Interpreter::new();
+        interpreter.execute(&root)?;
+
+        Ok(Self {
+            interpreter
+        })
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<&SupportedTypeBox> {
+        self.interpreter.get(identifier)
+    }
+}
@@ -0,0 +1,333 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// runs the binary with the source on the standard input.
+fn run_with_stdin(arguments: &[&str], source: &str) -> Output {
+    run_logging_with_stdin("error", arguments, source)
+}
+
+/// same as [run_with_stdin], but logs at `log_level` to the standard error.
+fn run_logging_with_stdin(log_level: &str, arguments: &[&str], source: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_neosvr-dsl"))
+        .args(["--log-level", log_level, "--color-policy", "never"])
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start the binary");
+    child.stdin.take().expect("stdin is piped").write_all(source.as_bytes()).expect("failed to write the source");
+
+    child.wait_with_output().expect("failed to wait for the binary")
+}
+
+#[test]
+fn run_evaluates_an_expression_statement() {
+    let output = run_with_stdin(&["run", "-"], "1 + 2\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn run_prints_declarations_and_expressions_in_order() {
+    let output = run_with_stdin(&["run", "-"], "var a = 2\na * 3\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n6\n");
+}
+
+#[test]
+fn run_exits_with_1_on_an_execution_error() {
+    let output = run_with_stdin(&["--error-format", "json", "run", "-"], "var a = b\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(r#""severity":"error""#));
+}
+
+#[test]
+fn missing_parent_directories_of_the_log_file_are_created() {
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("log_file_parents");
+    let _ = std::fs::remove_dir_all(&directory);
+    let log_file = directory.join("a").join("b").join("neosvr-dsl.log");
+    let source_file = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("log_file_parents.lgx");
+    std::fs::write(&source_file, "var a = b\n").expect("failed to write the source");
+
+    let output = run_logging_with_stdin("error", &["--log-file", log_file.to_str().expect("the path is UTF-8"), "stats", source_file.to_str().expect("the path is UTF-8")], "");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(log_file.is_file());
+}
+
+#[test]
+fn logger_which_cannot_be_set_up_is_a_usage_error() {
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("log_file_under_a_file");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).expect("failed to create the directory");
+    let file = directory.join("not_a_directory");
+    std::fs::write(&file, "").expect("failed to write the file");
+
+    let log_file = file.join("neosvr-dsl.log");
+    let output = run_logging_with_stdin("error", &["--log-file", log_file.to_str().expect("the path is UTF-8"), "stats", "-"], "var a = b\n");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("failed to set up the logger: "));
+}
+
+#[test]
+fn dash_reads_the_standard_input_instead_of_a_file() {
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("dash_path");
+    std::fs::create_dir_all(&directory).expect("failed to create the directory");
+    std::fs::write(directory.join("-"), "2\n").expect("failed to write the file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_neosvr-dsl"))
+        .current_dir(&directory)
+        .args(["--log-level", "error", "--color-policy", "never", "run", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().expect("stdin is piped").write_all(b"1\n")?;
+            child.wait_with_output()
+        })
+        .expect("failed to run the binary");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+}
+
+#[test]
+fn generate_stub_reads_the_standard_input() {
+    let output = run_with_stdin(&["generate-stub", "-"], r#"{ "name": "N", "outputs": [{ "name": "value", "type": "i32" }] }"#);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "// N\nvar value: i32 = N.value\n");
+}
+
+#[test]
+fn dump_tokens_writes_spans_as_text_or_json() {
+    let output = run_with_stdin(&["dump-tokens", "-"], "a = \"é\"\n");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().nth(2), Some("4..7 StringLiteral { content: \"é\" }"));
+
+    let output = run_with_stdin(&["dump-tokens", "--json", "-"], "a = 1\n");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let tokens: serde_json::Value = serde_json::from_slice(&output.stdout).expect("the output is JSON");
+    assert_eq!(tokens[0], serde_json::json!({ "token": { "Identifier": { "inner": "a" } }, "span": { "start": 0, "end": 1 } }));
+    assert_eq!(tokens[4]["token"], "EndOfFile");
+}
+
+#[test]
+fn log_is_colored_under_auto_only_if_stderr_is_a_terminal() {
+    let stderr_under = |color_policy: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_neosvr-dsl"))
+            .args(["--log-level", "error", "--color-policy", color_policy, "run", "missing.lgx"])
+            .output()
+            .expect("failed to run the binary");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    // the standard error is piped here
+    assert!(!stderr_under("auto").contains('\u{1b}'), "{}", stderr_under("auto"));
+    assert!(stderr_under("always").contains('\u{1b}'));
+}
+
+#[test]
+fn max_errors_stops_reporting_after_that_many_errors() {
+    let source = "var = 1\nvar = 2\nvar = 3\n";
+    let output = run_with_stdin(&["--error-format", "json", "--max-errors", "2", "compile", "-"], source);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr).lines().count(), 2);
+
+    let output = run_with_stdin(&["--error-format", "json", "compile", "-"], source);
+    assert_eq!(String::from_utf8_lossy(&output.stderr).lines().count(), 3);
+}
+
+#[test]
+fn human_error_format_locates_the_error_by_line_and_column() {
+    let output = run_with_stdin(&["run", "-"], "var a = 1\nvar = 2\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected an identifier after `var` (at 2:5)"), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn type_error_is_reported_as_json_before_compiling() {
+    let output = run_with_stdin(&["--error-format", "json", "compile", "-"], "var a = \"a\" - 1\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "{\"message\":\"Subtract cannot be applied to string and i32\",\"severity\":\"error\",\"span\":{\"file\":\"-\",\"line\":1,\"column\":9,\"byte_start\":8,\"byte_end\":15}}\n"
+    );
+}
+
+#[test]
+fn deep_nesting_is_reported_instead_of_overflowing_the_stack() {
+    let source = format!("{open}1{close}\n", open = "(".repeat(1100), close = ")".repeat(1100));
+    let output = run_with_stdin(&["run", "-"], &source);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the expression is nested too deeply (the limit is 1024)"));
+}
+
+#[test]
+fn nesting_within_the_default_limit_is_evaluated() {
+    let source = format!("{open}1{close}\n", open = "(".repeat(1000), close = ")".repeat(1000));
+    let output = run_with_stdin(&["run", "-"], &source);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+}
+
+#[test]
+fn chain_of_conditionals_counts_as_nesting() {
+    let chain = |length: usize| format!("{}1\n", "false ? 0 : ".repeat(length));
+
+    let output = run_with_stdin(&["run", "-"], &chain(1100));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the expression is nested too deeply (the limit is 1024)"));
+
+    let output = run_with_stdin(&["run", "-"], &chain(1000));
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+}
+
+#[test]
+fn deeply_nested_blocks_are_reported_instead_of_overflowing_the_stack() {
+    for (open, close) in [("if true then\n", "end\n"), ("while false\n", "wend\n")] {
+        let source = format!("{}1\n{}", open.repeat(20000), close.repeat(20000));
+        let output = run_with_stdin(&["run", "-"], &source);
+
+        assert_eq!(output.status.code(), Some(1), "{open}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("the expression is nested too deeply (the limit is 1024)"));
+    }
+}
+
+#[test]
+fn run_takes_the_default_int() {
+    let output = run_with_stdin(&["run", "--default-int", "u8", "-"], "var a = 255\nvar b = 256\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`256` is out of range for u8"));
+}
+
+#[test]
+fn json_emit_takes_the_default_int() {
+    let output = run_with_stdin(&["compile", "--emit", "json", "--default-int", "i64", "-"], "var a = 5\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(r#""type_tag": "i64""#));
+}
+
+#[test]
+fn unsuffixed_literal_out_of_the_default_int_is_rejected_by_every_emit_stage() {
+    for emit in ["json", "graph"] {
+        let output = run_with_stdin(&["compile", "--emit", emit, "--default-int", "u32", "-"], "var a = -1\n");
+
+        assert_eq!(output.status.code(), Some(1), "--emit {emit}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("`-1` is out of range for u32"), "--emit {emit}");
+    }
+}
+
+#[test]
+fn compile_writes_the_node_graph_by_default() {
+    let output = run_with_stdin(&["compile", "-"], "var a = 1 + 2\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), include_str!("fixtures/add.graph.json"));
+}
+
+#[test]
+fn tokens_emit_writes_each_token_with_its_span() {
+    let output = run_with_stdin(&["compile", "--emit", "tokens", "-"], "var a = 1 + 2\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\
+0..3 VarKeyword
+4..5 Identifier { inner: \"a\" }
+6..7 SymEq
+8..9 Digits { sequence: \"1\", suffix: None }
+10..11 SymPlus
+12..13 Digits { sequence: \"2\", suffix: None }
+13..14 NewLine
+14..14 EndOfFile
+");
+}
+
+#[test]
+fn ast_emit_writes_the_parsed_tree() {
+    let output = run_with_stdin(&["compile", "--emit", "ast", "-"], "var a = 1 + 2\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), include_str!("fixtures/add.ast.txt"));
+}
+
+#[test]
+fn fold_constants_leaves_an_operator_which_is_never_evaluated() {
+    // `1 / 0` is skipped by the short circuit and by the false condition, so it must not fail the compilation
+    let source = "var b = false\nvar a = b && 1 / 0 == 0\nif b then\nvar c = 1 / 0\nend\n";
+    let output = run_with_stdin(&["compile", "--fold-constants", "--emit", "ast", "-"], source);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).matches("Divide").count(), 2);
+}
+
+#[test]
+fn unknown_emit_stage_is_a_usage_error() {
+    let output = run_with_stdin(&["compile", "--emit", "bin", "-"], "var a = 1\n");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn compile_exits_with_1_on_a_syntax_error() {
+    let output = run_with_stdin(&["compile", "-"], "var = 1\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn large_source_file_is_compiled_as_the_standard_input_is() {
+    let source = (0..100_000).map(|i| format!("var a{i} = {i}\n")).collect::<String>();
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("large_source.lgx");
+    std::fs::write(&path, &source).expect("failed to write the source");
+
+    let from_file = run_with_stdin(&["compile", path.to_str().expect("the path is UTF-8")], "");
+    let from_stdin = run_with_stdin(&["compile", "-"], &source);
+
+    assert!(from_file.status.success(), "{}", String::from_utf8_lossy(&from_file.stderr));
+    assert!(from_stdin.status.success(), "{}", String::from_utf8_lossy(&from_stdin.stderr));
+    assert!(from_file.stdout == from_stdin.stdout, "the outputs differ");
+}
+
+#[test]
+fn decompress_exits_with_2_when_the_file_does_not_end_with_gz() {
+    let output = run_with_stdin(&["decompress", "sample.lgx"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("sample.lgx does not end with .gz"));
+}
+
+#[test]
+fn run_mixes_numeric_types_only_with_numeric_promotion() {
+    let output = run_with_stdin(&["run", "-"], "1i32 + 2i64\n");
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = run_with_stdin(&["run", "--numeric-promotion", "-"], "1i32 + 2i64\n");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn run_warns_about_implicit_widening_when_asked() {
+    let output = run_logging_with_stdin("warning", &["run", "--warn-implicit-widening", "-"], "var x: i64 = 5i32\n");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`x` is implicitly widened from i32 to i64"));
+}
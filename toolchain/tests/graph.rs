@@ -0,0 +1,17 @@
+use neosvr_dsl::compiler::graph::NodeGraph;
+use neosvr_dsl::compiler::lexer::Lexer;
+use neosvr_dsl::compiler::parser::{Parser, RootAst};
+use neosvr_dsl::compiler::resolved_type_tag::TypeTag;
+
+/// what `compile --emit graph` writes.
+fn emit_graph(source: &str) -> String {
+    let root = Parser::with_lexer(Lexer::from_borrowed(source)).parse::<RootAst>().expect("the source is valid");
+    let graph = NodeGraph::compile(&root, &TypeTag::I32).expect("the source can be compiled");
+
+    serde_json::to_string_pretty(&graph).expect("failed to serialize") + "\n"
+}
+
+#[test]
+fn addition_matches_the_golden_file() {
+    assert_eq!(emit_graph("var a = 1 + 2\n"), include_str!("fixtures/add.graph.json"));
+}
@@ -0,0 +1,72 @@
+use neosvr_dsl::interpreter::{Interpreter, InterpreterError, SupportedTypeBox};
+use neosvr_dsl::test_interpreter::TestInterpreter;
+
+#[test]
+fn run_returns_values_in_source_order() {
+    let values = Interpreter::run("var a = 1\nvar b = a + 2\nb * 2\n").expect("the program is valid");
+
+    assert_eq!(values, [SupportedTypeBox::I32(1), SupportedTypeBox::I32(3), SupportedTypeBox::I32(6)]);
+}
+
+#[test]
+fn run_reports_syntax_errors() {
+    let Err(InterpreterError::SyntaxError(errors)) = Interpreter::run("var = 1\n") else {
+        panic!("the program is malformed")
+    };
+
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn run_reports_execution_errors() {
+    let error = Interpreter::run("var a = b\n").expect_err("`b` is not declared");
+
+    assert!(matches!(error, InterpreterError::ExecutionError(_)));
+    assert!(error.to_string().starts_with("execution failed: "), "{error}");
+}
+
+#[test]
+fn test_interpreter_keeps_the_scope() {
+    let interpreter = TestInterpreter::create_and_execute("var greeting = \"hello\"\nvar count = 2 * 3\n").expect("the program is valid");
+
+    assert_eq!(interpreter.get("greeting").and_then(SupportedTypeBox::get_string), Some("hello"));
+    assert_eq!(interpreter.get("count").and_then(SupportedTypeBox::get_i32), Some(6));
+    assert_eq!(interpreter.get("missing"), None);
+}
+
+#[test]
+fn values_are_displayed_as_in_the_source() {
+    let values = Interpreter::run("\"a\"\n1.5\ntrue\n[1, 2]\n").expect("the program is valid");
+    let displayed = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    assert_eq!(displayed, ["\"a\"", "1.5", "true", "[1, 2]"]);
+}
+
+#[test]
+fn later_declaration_refers_to_an_earlier_one() {
+    let interpreter = TestInterpreter::create_and_execute("var a = 1\nvar b = a + 2\n").expect("the program is valid");
+
+    assert_eq!(interpreter.get("a"), Some(&SupportedTypeBox::I32(1)));
+    assert_eq!(interpreter.get("b"), Some(&SupportedTypeBox::I32(3)));
+}
+
+#[test]
+fn syntax_error_keeps_every_parse_error() {
+    let error = Interpreter::run("var = 1\nvar 2 = 3\n").expect_err("the program is malformed");
+    let InterpreterError::SyntaxError(errors) = &error else {
+        panic!("the program should not be executed")
+    };
+
+    assert_eq!(errors.iter().map(|e| e.span().start).collect::<Vec<_>>(), [4, 12]);
+    assert_eq!(error.to_string(), format!("the program has a syntax error: {}; {}", errors[0], errors[1]));
+}
+
+#[test]
+fn test_interpreter_reports_what_the_parser_found() {
+    let Err(InterpreterError::SyntaxError(errors)) = TestInterpreter::create_and_execute("var a = 1\nvar = 2\n") else {
+        panic!("the program is malformed")
+    };
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "SymEq is unexpected, expected an identifier after `var`");
+}